@@ -1,10 +1,10 @@
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::DeriveInput;
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{ext::IdentExt, parse_quote, spanned::Spanned, Attribute, DeriveInput, Ident};
 
 use crate::internals::{
     ast::{self, Container},
-    Context,
+    Context, Elementwise, FieldDefault,
 };
 
 pub fn derive_patch(input: &mut DeriveInput) -> Result<TokenStream, syn::Error> {
@@ -15,129 +15,2060 @@ pub fn derive_patch(input: &mut DeriveInput) -> Result<TokenStream, syn::Error>
     };
     context.check()?;
 
-    let patch_struct = expand_patch_struct(&container);
+    let patch_struct = if container.attrs.compact() {
+        expand_compact_patch_struct(&container)
+    } else {
+        expand_patch_struct(&container)
+    };
+    let setters = expand_setters(&container);
+    let accessors = expand_accessors(&container);
     let impl_patch_trait = expand_patch_trait(&container);
+    let forward_patch_impls = expand_forward_patch_impls(&container);
+    let remote_check = expand_remote_check(&container);
+    let impl_expect_trait = expand_expect_trait(&container);
+    let impl_try_merge_trait = expand_try_merge_trait(&container);
+    let impl_patch_ref_trait = expand_patch_ref_trait(&container);
+    let impl_patch_notify_trait = expand_patch_notify_trait(&container);
+    let impl_minimize_trait = expand_minimize_trait(&container);
+    let impl_algebra_traits = expand_algebra_traits(&container);
+    let mask_struct = expand_mask_struct(&container);
+    let impl_try_from_trait = expand_try_from_trait(&container);
+
+    #[cfg(feature = "schemars")]
+    let impl_json_schema = expand_json_schema(&container);
+    #[cfg(not(feature = "schemars"))]
+    let impl_json_schema = TokenStream::new();
+
+    #[cfg(feature = "wire")]
+    let impl_wire_traits = expand_wire_traits(&container);
+    #[cfg(not(feature = "wire"))]
+    let impl_wire_traits = TokenStream::new();
+
+    #[cfg(feature = "serde")]
+    let impl_deserialize_patch_trait = expand_deserialize_patch_trait(&container);
+    #[cfg(not(feature = "serde"))]
+    let impl_deserialize_patch_trait = TokenStream::new();
+
+    #[cfg(feature = "proptest")]
+    let impl_arbitrary_trait = expand_arbitrary_trait(&container);
+    #[cfg(not(feature = "proptest"))]
+    let impl_arbitrary_trait = TokenStream::new();
 
     Ok(quote! {
         #patch_struct
+        #setters
+        #accessors
         #impl_patch_trait
+        #forward_patch_impls
+        #remote_check
+        #impl_expect_trait
+        #impl_try_merge_trait
+        #impl_patch_ref_trait
+        #impl_patch_notify_trait
+        #impl_minimize_trait
+        #impl_algebra_traits
+        #mask_struct
+        #impl_try_from_trait
+        #impl_json_schema
+        #impl_wire_traits
+        #impl_deserialize_patch_trait
+        #impl_arbitrary_trait
     })
 }
 
 fn expand_patch_trait(container: &Container) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
 
-    let base_struct_name = container.ident;
+    let base_ty = expand_base_ty(container);
     let patch_struct_name = container.attrs.patch_struct_name();
 
-    let patch_fields = container.fields.iter().map(|field| {
+    let patch_fields = expand_field_statements(&container.fields, |field| {
         let member = &field.member;
+        let value = if container.attrs.compact() {
+            expand_compact_take(field, quote!(patch))
+        } else {
+            quote!(patch.#member)
+        };
+        quote! { patched::Patch::patch(&mut self.#member, #value); }
+    });
+    // The fields of a compact patch struct are taken out of it.
+    let patch_arg = if container.attrs.compact() {
+        quote!(mut patch)
+    } else {
+        quote!(patch)
+    };
+
+    // `Patchable` cannot be implemented for a remote type because of the orphan rule.
+    let impl_patchable_trait = container.attrs.remote().is_none().then(|| {
         quote! {
-            patched::Patch::patch(&mut self.#member, patch.#member);
+            #[automatically_derived]
+            impl #impl_generics patched::Patchable for #base_ty #where_clause {
+                type Patch = #patch_struct_name #ty_generics;
+            }
         }
     });
 
     quote! {
         #[automatically_derived]
-        impl #impl_generics patched::Patch<#patch_struct_name #ty_generics> for #base_struct_name #ty_generics #where_clause {
+        #[allow(deprecated)]
+        impl #impl_generics patched::Patch<#patch_struct_name #ty_generics> for #base_ty #where_clause {
             #[inline]
-            fn patch(&mut self, patch: #patch_struct_name #ty_generics) {
-                #(#patch_fields)*
+            fn patch(&mut self, #patch_arg: #patch_struct_name #ty_generics) {
+                #patch_fields
             }
         }
+
+        #impl_patchable_trait
     }
 }
 
-fn expand_patch_struct(container: &Container) -> TokenStream {
-    let base_struct_name = container.ident;
-    let vis = container.vis;
+/// Implements `Patch` for the smart pointers and cells of the patched type, so a field of type
+/// `Box<Foo>`, `Rc<Foo>`, `Arc<Foo>`, `Cow<'_, Foo>` or `RefCell<Foo>` can be patched with
+/// `#[patch(with = FooPatch)]`. An empty patch is skipped, so a shared value is not copied.
+fn expand_forward_patch_impls(container: &Container) -> TokenStream {
+    let (_, ty_generics, _) = container.generics.split_for_impl();
     let patch_struct_name = container.attrs.patch_struct_name();
-    let patch_struct_attributes = container.attrs.path_struct_attributes();
-    let generics = container.generics;
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
 
-    let fields = match container.style {
-        crate::internals::ast::Style::Struct => {
-            let fields = container.fields.iter().map(|field| {
-                let name = &field.member;
-                let ty = field.attrs.patch_ty();
-                let attrs = field.attrs.path_field_attributes();
-                quote! { #(#attrs)* pub #name: #ty }
+    expand_forward_impls(
+        container,
+        container.generics,
+        Access::Mut,
+        TokenStream::new(),
+        |ty, target| {
+            let header = quote!(patched::Patch<#patch_ty> for #ty);
+            let items = quote! {
+                #[inline]
+                fn patch(&mut self, patch: #patch_ty) {
+                    if !patched::Merge::is_empty(&patch) {
+                        patched::Patch::patch(#target, patch);
+                    }
+                }
+            };
+            (header, items)
+        },
+    )
+}
+
+/// How a forwarded impl reaches the patched value of a smart pointer or a cell.
+#[derive(Clone, Copy)]
+enum Access {
+    /// A mutable reference to the value of `self`, which is cloned on write if it's shared.
+    Mut,
+    /// A shared reference to the value of the given expression.
+    Ref,
+    /// A function wrapping a new value.
+    New,
+}
+
+/// Implements a trait of the patched type for its smart pointers and cells, `Box<Foo>`,
+/// `Rc<Foo>`, `Arc<Foo>`, `Cow<'_, Foo>` and `RefCell<Foo>`, by forwarding to the patched value.
+///
+/// `expand` returns the trait and the type following the generics of an impl, and its items,
+/// given the type of the smart pointer and the access to its value.
+fn expand_forward_impls(
+    container: &Container,
+    generics: &syn::Generics,
+    access: Access,
+    value: TokenStream,
+    expand: impl Fn(&TokenStream, &TokenStream) -> (TokenStream, TokenStream),
+) -> TokenStream {
+    let base_ty = expand_base_ty(container);
+
+    // A `Cow<'_, Foo>` requires `Foo: Clone`, and so do `Rc` and `Arc` to be cloned on write.
+    // The bound is put behind a binder so it's not rejected when it doesn't hold for a
+    // non-generic type: the impl is then never applicable.
+    let mut clone_generics = generics.clone();
+    clone_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(for<'__patched> #base_ty: ::core::clone::Clone));
+    let mut cow_generics = clone_generics.clone();
+    cow_generics.params.insert(0, parse_quote!('__cow));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, _, clone_where_clause) = clone_generics.split_for_impl();
+    let (cow_impl_generics, _, cow_where_clause) = cow_generics.split_for_impl();
+    let shared_where_clause = match access {
+        Access::Mut => clone_where_clause,
+        Access::Ref | Access::New => where_clause,
+    };
+
+    let targets = [
+        (
+            quote!(#impl_generics),
+            quote!(::std::boxed::Box<#base_ty>),
+            quote!(#where_clause),
+            [
+                quote!(&mut **self),
+                quote!(&**#value),
+                quote!(::std::boxed::Box::new),
+            ],
+        ),
+        (
+            quote!(#impl_generics),
+            quote!(::std::rc::Rc<#base_ty>),
+            quote!(#shared_where_clause),
+            [
+                quote!(::std::rc::Rc::make_mut(self)),
+                quote!(&**#value),
+                quote!(::std::rc::Rc::new),
+            ],
+        ),
+        (
+            quote!(#impl_generics),
+            quote!(::std::sync::Arc<#base_ty>),
+            quote!(#shared_where_clause),
+            [
+                quote!(::std::sync::Arc::make_mut(self)),
+                quote!(&**#value),
+                quote!(::std::sync::Arc::new),
+            ],
+        ),
+        (
+            quote!(#cow_impl_generics),
+            quote!(::std::borrow::Cow<'__cow, #base_ty>),
+            quote!(#cow_where_clause),
+            [
+                quote!(::std::borrow::Cow::to_mut(self)),
+                quote!(&**#value),
+                quote!(::std::borrow::Cow::Owned),
+            ],
+        ),
+        (
+            quote!(#impl_generics),
+            quote!(::core::cell::RefCell<#base_ty>),
+            quote!(#where_clause),
+            [
+                quote!(::core::cell::RefCell::get_mut(self)),
+                quote!(&*::core::cell::RefCell::borrow(#value)),
+                quote!(::core::cell::RefCell::new),
+            ],
+        ),
+    ];
+
+    let impls =
+        targets
+            .into_iter()
+            .map(|(impl_generics, ty, where_clause, [get_mut, get, new])| {
+                let target = match access {
+                    Access::Mut => get_mut,
+                    Access::Ref => get,
+                    Access::New => new,
+                };
+                let (header, items) = expand(&ty, &target);
+                quote! {
+                    #[automatically_derived]
+                    impl #impl_generics #header #where_clause {
+                        #items
+                    }
+                }
             });
-            quote! {
-                { #(#fields),* }
+
+    quote! {
+        #(#impls)*
+    }
+}
+
+/// Implements `PatchRef` with `#[patch(by_ref)]`, also through the smart pointers.
+fn expand_patch_ref_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_patch_ref_trait() {
+        return TokenStream::new();
+    }
+
+    let generics = with_field_bounds(container, |field| {
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#ty: patched::PatchRef<#patch_ty>)
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
+
+    let patch_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! { patched::PatchRef::patch_ref(&mut self.#member, &patch.#member); }
+    });
+
+    let forward_impls = expand_forward_impls(
+        container,
+        &generics,
+        Access::Mut,
+        TokenStream::new(),
+        |ty, target| {
+            let header = quote!(patched::PatchRef<#patch_ty> for #ty);
+            let items = quote! {
+                #[inline]
+                fn patch_ref(&mut self, patch: &#patch_ty) {
+                    if !patched::Merge::is_empty(patch) {
+                        patched::PatchRef::patch_ref(#target, patch);
+                    }
+                }
+            };
+            (header, items)
+        },
+    );
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::PatchRef<#patch_ty> for #base_ty #where_clause {
+            #[inline]
+            fn patch_ref(&mut self, patch: &#patch_ty) {
+                #patch_fields
             }
         }
-        crate::internals::ast::Style::Tuple => {
-            let fields = container.fields.iter().map(|field| {
-                let ty = field.attrs.patch_ty();
-                let attrs = field.attrs.path_field_attributes();
-                quote! { #(#attrs)* pub #ty }
+
+        #forward_impls
+    }
+}
+
+/// Implements `FromPatch`, also through the smart pointers, and `TryFrom<FooPatch>` with
+/// `#[patch(try_into)]`.
+fn expand_try_from_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_try_from_trait() {
+        return TokenStream::new();
+    }
+
+    let base_ty = expand_base_ty(container);
+    let mut generics = with_field_bounds(container, |field| {
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        if container.attrs.try_into_default() || field.attrs.default().is_some() {
+            parse_quote!(#ty: patched::Patch<#patch_ty>)
+        } else {
+            parse_quote!(#ty: patched::FromPatch<#patch_ty>)
+        }
+    });
+    if container.attrs.try_into_default() {
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#base_ty: ::core::default::Default));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
+
+    let body = if container.attrs.try_into_default() {
+        quote! {
+            ::core::option::Option::Some(patched::Patch::with_patch(
+                <Self as ::core::default::Default>::default(),
+                patch,
+            ))
+        }
+    } else {
+        let field_var = |field: &ast::Field| format_ident!("__{}", field_name(field));
+
+        let fields = container.fields.iter().map(|field| {
+            let member = &field.member;
+            let name = field_name(field);
+            let var = field_var(field);
+            let ty = &field.ty;
+            let patch_ty = field.attrs.patch_ty();
+
+            let default = field.attrs.default().map(|default| match default {
+                FieldDefault::Default => quote!(::core::default::Default::default()),
+                FieldDefault::Expr(expr) => quote!(#expr),
             });
+
+            let value = match default {
+                None => quote! {
+                    {
+                        path.push(#name);
+                        let value = <#ty as patched::FromPatch<#patch_ty>>::from_patch_at(
+                            patch.#member,
+                            path,
+                            missing,
+                        );
+                        path.pop();
+                        value
+                    }
+                },
+                // The missing fields of a nested patch are taken from the default value.
+                Some(default) if field.attrs.with().is_some() => quote! {
+                    ::core::option::Option::Some({
+                        let mut value: #ty = #default;
+                        patched::Patch::patch(&mut value, patch.#member);
+                        value
+                    })
+                },
+                Some(default) => quote! {
+                    ::core::option::Option::Some(match patch.#member {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => #default,
+                    })
+                },
+            };
+
             quote! {
-                ( #(#fields),* );
+                let #var = #value;
             }
+        });
+
+        let instantiate_fields =
+            expand_instantiate_fields(container.style, &container.fields, |field| {
+                let var = field_var(field);
+                quote!(#var?)
+            });
+
+        quote! {
+            #(#fields)*
+            ::core::option::Option::Some(Self #instantiate_fields)
         }
-        crate::internals::ast::Style::Unit => quote!(;),
     };
 
+    let forward_impls = expand_forward_impls(
+        container,
+        &generics,
+        Access::New,
+        TokenStream::new(),
+        |ty, new| {
+            let header = quote!(patched::FromPatch<#patch_ty> for #ty);
+            let items = quote! {
+                #[inline]
+                fn from_patch_at(
+                    patch: #patch_ty,
+                    path: &mut patched::FieldPath,
+                    missing: &mut ::std::vec::Vec<patched::FieldPath>,
+                ) -> ::core::option::Option<Self> {
+                    <#base_ty as patched::FromPatch<#patch_ty>>::from_patch_at(patch, path, missing)
+                        .map(#new)
+                }
+            };
+            (header, items)
+        },
+    );
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::FromPatch<#patch_ty> for #base_ty #where_clause {
+            #[allow(unused_variables)]
+            fn from_patch_at(
+                patch: #patch_ty,
+                path: &mut patched::FieldPath,
+                missing: &mut ::std::vec::Vec<patched::FieldPath>,
+            ) -> ::core::option::Option<Self> {
+                #body
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::core::convert::TryFrom<#patch_ty> for #base_ty #where_clause {
+            type Error = patched::MissingFields;
+
+            #[inline]
+            fn try_from(patch: #patch_ty) -> ::core::result::Result<Self, Self::Error> {
+                <Self as patched::FromPatch<#patch_ty>>::try_from_patch(patch)
+            }
+        }
+
+        #forward_impls
+    }
+}
+
+/// Implements `PatchNotify` with `#[patch(notify)]`, also through the smart pointers.
+fn expand_patch_notify_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_patch_notify_trait() {
+        return TokenStream::new();
+    }
+
+    let generics = with_field_bounds(container, |field| {
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#ty: patched::PatchNotify<#patch_ty>)
+    });
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
 
-    let default_fields = expand_instantiate_fields(container.style, &container.fields, |_| {
-        quote! { ::core::default::Default::default() }
+    let notify_fields = expand_field_path_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! {
+            patched::PatchNotify::patch_notify_at(&mut self.#member, patch.#member, path, observer);
+        }
     });
 
-    let merge_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
-        let name = &field.member;
-        quote! { patched::Merge::merge(self.#name, rhs.#name) }
+    let forward_impls = expand_forward_impls(
+        container,
+        &generics,
+        Access::Mut,
+        TokenStream::new(),
+        |ty, target| {
+            let header = quote!(patched::PatchNotify<#patch_ty> for #ty);
+            let items = quote! {
+                #[inline]
+                fn patch_notify_at(
+                    &mut self,
+                    patch: #patch_ty,
+                    path: &mut patched::FieldPath,
+                    observer: &mut dyn ::core::ops::FnMut(patched::Change<'_>),
+                ) {
+                    if !patched::Merge::is_empty(&patch) {
+                        patched::PatchNotify::patch_notify_at(#target, patch, path, observer);
+                    }
+                }
+            };
+            (header, items)
+        },
+    );
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::PatchNotify<#patch_ty> for #base_ty #where_clause {
+            fn patch_notify_at(
+                &mut self,
+                patch: #patch_ty,
+                path: &mut patched::FieldPath,
+                observer: &mut dyn ::core::ops::FnMut(patched::Change<'_>),
+            ) {
+                #notify_fields
+            }
+        }
+
+        #forward_impls
+    }
+}
+
+/// Implements `Minimize` for the patch struct with `#[patch(minimize)]`, also against the smart
+/// pointers.
+fn expand_minimize_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_minimize_trait() {
+        return TokenStream::new();
+    }
+
+    let generics = with_field_bounds(container, |field| {
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#patch_ty: patched::Minimize<#ty>)
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
+    let vis = patch_struct_vis(container);
+
+    let minimize_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! {
+            patched::Minimize::minimize_in_place(&mut self.#member, &current.#member);
+        }
     });
 
-    let impl_from_trait = container.attrs.impl_from_trait().then(|| {
-        let from_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+    let noop_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! {
+            if !patched::Minimize::is_noop_for(&self.#member, &current.#member) {
+                return false;
+            }
+        }
+    });
+
+    let forward_impls = expand_forward_impls(
+        container,
+        &generics,
+        Access::Ref,
+        quote!(current),
+        |ty, target| {
+            let header = quote!(patched::Minimize<#ty> for #patch_ty);
+            let items = quote! {
+                #[inline]
+                fn minimize_in_place(&mut self, current: &#ty) {
+                    patched::Minimize::minimize_in_place(self, #target);
+                }
+
+                #[inline]
+                fn is_noop_for(&self, current: &#ty) -> bool {
+                    patched::Minimize::is_noop_for(self, #target)
+                }
+            };
+            (header, items)
+        },
+    );
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::Minimize<#base_ty> for #patch_ty #where_clause {
+            fn minimize_in_place(&mut self, current: &#base_ty) {
+                #minimize_fields
+            }
+
+            fn is_noop_for(&self, current: &#base_ty) -> bool {
+                #noop_fields
+                true
+            }
+        }
+
+        // The methods of `Minimize`, without importing the trait.
+        #[automatically_derived]
+        #[allow(dead_code)]
+        impl #impl_generics #patch_ty #where_clause {
+            /// Consumes `self` and returns a version without the fields that wouldn't change
+            /// `current`.
+            #[inline]
+            #[must_use]
+            #vis fn minimize<__T>(self, current: &__T) -> Self
+            where
+                Self: patched::Minimize<__T>,
+            {
+                patched::Minimize::minimize(self, current)
+            }
+
+            /// Drops the fields of `self` that wouldn't change `current`.
+            #[inline]
+            #vis fn minimize_in_place<__T>(&mut self, current: &__T)
+            where
+                Self: patched::Minimize<__T>,
+            {
+                patched::Minimize::minimize_in_place(self, current)
+            }
+
+            /// Returns whether applying `self` to `current` would leave it unchanged.
+            #[inline]
+            #vis fn is_noop_for<__T>(&self, current: &__T) -> bool
+            where
+                Self: patched::Minimize<__T>,
+            {
+                patched::Minimize::is_noop_for(self, current)
+            }
+        }
+
+        #forward_impls
+    }
+}
+
+/// Generates the mask struct with `#[patch(mask)]`, and implements `Masked` and `MaskedFrom` for
+/// the patch struct.
+///
+/// The mask struct stores the fields without nested patches in a bitset, and the masks of the
+/// others in public fields, it isn't generic since these masks don't use the type parameters.
+fn expand_mask_struct(container: &Container) -> TokenStream {
+    let Some(mask_struct_name) = container.attrs.mask_struct_name() else {
+        return TokenStream::new();
+    };
+
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+    let vis = patch_struct_vis(container);
+    let doc = format!("The fields of `{}`.", container.ident);
+    let (field_bits, bits_len) = field_bits(&container.fields);
+    let bit_of = |field: &ast::Field| {
+        let index = container
+            .fields
+            .iter()
+            .position(|other| other.member == field.member);
+        field_bits[index.unwrap()]
+    };
+
+    // The masks of the nested patches, named after the fields or their index.
+    let nested_fields = container
+        .fields
+        .iter()
+        .zip(&field_bits)
+        .filter(|(_, bit)| bit.is_none())
+        .map(|(field, _)| {
+            let name = mask_field_name(field);
+            let patch_ty = field.attrs.patch_ty();
+            (field, name, quote!(<#patch_ty as patched::Masked>::Mask))
+        })
+        .collect::<Vec<_>>();
+    let fields = nested_fields.iter().map(|(field, name, ty)| {
+        let forwarded_attrs = forwarded_field_attributes(container, field);
+        let vis = &field.patch_vis;
+        quote! { #(#forwarded_attrs)* #vis #name: #ty }
+    });
+    let nested_names = nested_fields
+        .iter()
+        .map(|(_, name, _)| name)
+        .collect::<Vec<_>>();
+    let nested_tys = nested_fields
+        .iter()
+        .map(|(_, _, ty)| ty)
+        .collect::<Vec<_>>();
+
+    // Whether the bit of a field is set on `mask`.
+    let bit_is_set = |mask: TokenStream, (index, bit): (usize, u8)| quote!((#mask.__patched_bits[#index] & #bit != 0));
+    let all_bits = (0..bits_len).map(|index| {
+        field_bits
+            .iter()
+            .flatten()
+            .filter(|(i, _)| *i == index)
+            .fold(0u8, |bits, (_, bit)| bits | bit)
+    });
+    let bits_op = |op: TokenStream| {
+        let bytes = (0..bits_len)
+            .map(|index| quote!(self.__patched_bits[#index] #op other.__patched_bits[#index]));
+        quote!([#(#bytes),*])
+    };
+    let union_bits = bits_op(quote!(|));
+    let intersection_bits = bits_op(quote!(&));
+    let difference_bits = bits_op(quote!(&!));
+
+    let consts = container
+        .fields
+        .iter()
+        .zip(&field_bits)
+        .map(|(field, bit)| {
+            let vis = &field.patch_vis;
+            let const_name = field.mask_const_name();
+            let doc = format!("The mask of the field `{}`.", field_name(field));
+            let bytes = (0..bits_len).map(|index| match bit {
+                Some((i, bit)) if *i == index => *bit,
+                _ => 0,
+            });
+            let nested = nested_fields.iter().map(|(nested, name, ty)| {
+                if nested.member == field.member {
+                    quote!(#name: <#ty as patched::Mask>::ALL)
+                } else {
+                    quote!(#name: <#ty as patched::Mask>::EMPTY)
+                }
+            });
+            let value = quote!(Self { __patched_bits: [#(#bytes),*], #(#nested,)* });
+            quote! {
+                #[doc = #doc]
+                #vis const #const_name: Self = #value;
+            }
+        });
+
+    let insert_path_arms = expand_field_statements(&container.fields, |field| {
+        let name = field_name(field);
+        match bit_of(field) {
+            Some((index, bit)) => quote! {
+                [#name] => {
+                    self.__patched_bits[#index] |= #bit;
+                    true
+                }
+            },
+            None => {
+                let member = mask_field_name(field);
+                quote! { [#name, rest @ ..] => patched::Mask::insert_path(&mut self.#member, rest), }
+            }
+        }
+    });
+    let paths_fields = expand_field_path_statements(&container.fields, |field| {
+        let mask = match bit_of(field) {
+            Some(bit) => bit_is_set(quote!(self), bit),
+            None => {
+                let member = mask_field_name(field);
+                quote!(self.#member)
+            }
+        };
+        quote! { patched::Mask::paths_at(&#mask, path, paths); }
+    });
+    let debug_fields = container
+        .fields
+        .iter()
+        .zip(&field_bits)
+        .map(|(field, bit)| {
+            let name = field_name(field);
+            let mask = match bit {
+                Some(bit) => bit_is_set(quote!(self), *bit),
+                None => {
+                    let member = mask_field_name(field);
+                    quote!(self.#member)
+                }
+            };
+            quote! { .field(#name, &#mask) }
+        });
+
+    // The bits of the fields without nested patches are set in the statements, the masks of the
+    // others are the values of the fields.
+    let masked_statements = container
+        .fields
+        .iter()
+        .zip(&field_bits)
+        .map(|(field, bit)| {
+            let member = &field.member;
+            match bit {
+                Some((index, bit)) => quote! {
+                    if patched::Masked::mask(&self.#member) {
+                        mask.__patched_bits[#index] |= #bit;
+                    }
+                },
+                None => {
+                    let name = mask_field_name(field);
+                    quote! { mask.#name = patched::Masked::mask(&self.#member); }
+                }
+            }
+        });
+    let field_mask = |field: &ast::Field| match bit_of(field) {
+        Some(bit) => bit_is_set(quote!(mask), bit),
+        None => {
+            let name = mask_field_name(field);
+            quote!(mask.#name)
+        }
+    };
+    let retain_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+        let name = &field.member;
+        let mask = field_mask(field);
+        quote! { patched::Masked::retain(self.#name, #mask) }
+    });
+    let from_value_fields =
+        expand_instantiate_fields(container.style, &container.fields, |field| {
             let name = &field.member;
-            quote! { ::core::convert::From::from(value.#name) }
+            let mask = field_mask(field);
+            quote! { patched::MaskedFrom::from_value_masked(&value.#name, #mask) }
         });
 
+    // `MaskedFrom` is implemented only when the fields implement it, which requires `Clone`, the
+    // bounds are put behind a binder like for `MergeFrom`. The masks of the fields in the bitset
+    // are named in the bounds, which would hide them otherwise.
+    let mut from_value_generics = container.generics.clone();
+    from_value_generics.make_where_clause().predicates.extend(
+        container.fields.iter().zip(&field_bits).map(|(field, bit)| -> syn::WherePredicate {
+            let ty = &field.ty;
+            let patch_ty = field.attrs.patch_ty();
+            match bit {
+                Some(_) => {
+                    parse_quote!(for<'__patched> #patch_ty: patched::MaskedFrom<#ty, Mask = bool>)
+                }
+                None => parse_quote!(for<'__patched> #patch_ty: patched::MaskedFrom<#ty>),
+            }
+        }),
+    );
+    let (_, _, from_value_where_clause) = from_value_generics.split_for_impl();
+
+    let ops = [
+        (quote!(BitOr), quote!(bitor), quote!(union)),
+        (quote!(BitAnd), quote!(bitand), quote!(intersection)),
+        (quote!(Sub), quote!(sub), quote!(difference)),
+    ];
+    let impl_ops = ops.into_iter().map(|(op_trait, op_method, method)| {
         quote! {
             #[automatically_derived]
-            impl #impl_generics ::core::convert::From<#base_struct_name #ty_generics> for #patch_struct_name #ty_generics #where_clause {
+            impl ::core::ops::#op_trait for #mask_struct_name {
+                type Output = Self;
+
                 #[inline]
-                fn from(value: #base_struct_name #ty_generics) -> Self {
-                    Self #from_fields
+                fn #op_method(self, rhs: Self) -> Self {
+                    patched::Mask::#method(self, rhs)
                 }
             }
         }
     });
 
     quote! {
-        #(#patch_struct_attributes)*
-        #vis struct #patch_struct_name #generics #fields
+        #[doc = #doc]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #vis struct #mask_struct_name {
+            __patched_bits: [u8; #bits_len],
+            #(#fields,)*
+        }
+
+        #[allow(deprecated)]
+        impl #mask_struct_name {
+            #(#consts)*
+        }
 
         #[automatically_derived]
-        impl #impl_generics patched::Merge for #patch_struct_name #ty_generics #where_clause {
-            type Output = Self;
+        #[allow(deprecated)]
+        impl patched::Mask for #mask_struct_name {
+            const EMPTY: Self = Self {
+                __patched_bits: [0; #bits_len],
+                #(#nested_names: <#nested_tys as patched::Mask>::EMPTY,)*
+            };
+
+            const ALL: Self = Self {
+                __patched_bits: [#(#all_bits),*],
+                #(#nested_names: <#nested_tys as patched::Mask>::ALL,)*
+            };
 
             #[inline]
-            fn merge(self, rhs: Self) -> Self::Output {
-                Self #merge_fields
+            fn union(self, other: Self) -> Self {
+                Self {
+                    __patched_bits: #union_bits,
+                    #(#nested_names: patched::Mask::union(self.#nested_names, other.#nested_names),)*
+                }
+            }
+
+            #[inline]
+            fn intersection(self, other: Self) -> Self {
+                Self {
+                    __patched_bits: #intersection_bits,
+                    #(
+                        #nested_names:
+                            patched::Mask::intersection(self.#nested_names, other.#nested_names),
+                    )*
+                }
+            }
+
+            #[inline]
+            fn difference(self, other: Self) -> Self {
+                Self {
+                    __patched_bits: #difference_bits,
+                    #(
+                        #nested_names:
+                            patched::Mask::difference(self.#nested_names, other.#nested_names),
+                    )*
+                }
+            }
+
+            fn insert_path(&mut self, segments: &[&str]) -> bool {
+                match segments {
+                    [] => {
+                        *self = patched::Mask::all();
+                        true
+                    }
+                    #insert_path_arms
+                    _ => false,
+                }
+            }
+
+            fn paths_at(
+                &self,
+                path: &mut patched::FieldPath,
+                paths: &mut ::std::vec::Vec<patched::FieldPath>,
+            ) {
+                #paths_fields
             }
         }
 
         #[automatically_derived]
-        impl #impl_generics ::core::default::Default for #patch_struct_name #ty_generics #where_clause {
+        #[allow(deprecated)]
+        impl ::core::fmt::Debug for #mask_struct_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(::core::stringify!(#mask_struct_name))
+                    #(#debug_fields)*
+                    .finish()
+            }
+        }
+
+        #[automatically_derived]
+        impl ::core::default::Default for #mask_struct_name {
+            /// The mask without fields.
             #[inline]
             fn default() -> Self {
-                Self #default_fields
+                patched::Mask::empty()
             }
         }
 
-        #impl_from_trait
+        #(#impl_ops)*
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::Masked for #patch_struct_name #ty_generics #where_clause {
+            type Mask = #mask_struct_name;
+
+            #[inline]
+            fn mask(&self) -> Self::Mask {
+                let mut mask = <Self::Mask as patched::Mask>::EMPTY;
+                #(#masked_statements)*
+                mask
+            }
+
+            #[inline]
+            fn retain(self, mask: Self::Mask) -> Self {
+                Self #retain_fields
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::MaskedFrom<#base_ty> for #patch_struct_name #ty_generics
+        #from_value_where_clause
+        {
+            #[inline]
+            fn from_value_masked(value: &#base_ty, mask: Self::Mask) -> Self {
+                Self #from_value_fields
+            }
+        }
     }
 }
 
-fn expand_instantiate_fields(
-    style: ast::Style,
-    fields: &[ast::Field],
-    mut field_value: impl FnMut(&ast::Field) -> TokenStream,
+/// Implements `Expect` with `#[patch(expect)]`, also through the smart pointers.
+fn expand_expect_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_expect_trait() {
+        return TokenStream::new();
+    }
+
+    let generics = with_field_bounds(container, |field| {
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#ty: patched::Expect<#patch_ty>)
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_ty = quote!(#patch_struct_name #ty_generics);
+
+    let expect_fields = expand_field_path_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! {
+            patched::Expect::mismatches_at(&self.#member, &expected.#member, path, mismatches);
+        }
+    });
+
+    let forward_impls = expand_forward_impls(
+        container,
+        &generics,
+        Access::Ref,
+        quote!(self),
+        |ty, target| {
+            let header = quote!(patched::Expect<#patch_ty> for #ty);
+            let items = quote! {
+                #[inline]
+                fn mismatches_at(
+                    &self,
+                    expected: &#patch_ty,
+                    path: &mut patched::FieldPath,
+                    mismatches: &mut ::std::vec::Vec<patched::FieldPath>,
+                ) {
+                    patched::Expect::mismatches_at(#target, expected, path, mismatches);
+                }
+            };
+            (header, items)
+        },
+    );
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::Expect<#patch_ty> for #base_ty #where_clause {
+            fn mismatches_at(
+                &self,
+                expected: &#patch_ty,
+                path: &mut patched::FieldPath,
+                mismatches: &mut ::std::vec::Vec<patched::FieldPath>,
+            ) {
+                #expect_fields
+            }
+        }
+
+        #forward_impls
+    }
+}
+
+/// Implements `TryMerge` for the patch struct with `#[patch(try_merge)]`.
+fn expand_try_merge_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_try_merge_trait() {
+        return TokenStream::new();
+    }
+
+    let generics = with_field_bounds(container, |field| {
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#patch_ty: patched::TryMerge)
+    });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let patch_struct_name = container.attrs.patch_struct_name();
+
+    let conflict_fields = expand_field_path_statements(&container.fields, |field| {
+        let member = &field.member;
+        quote! {
+            patched::TryMerge::merge_conflicts_at(&self.#member, &rhs.#member, path, conflicts);
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::TryMerge for #patch_struct_name #ty_generics #where_clause {
+            fn merge_conflicts_at(
+                &self,
+                rhs: &Self,
+                path: &mut patched::FieldPath,
+                conflicts: &mut ::std::vec::Vec<patched::MergeConflict>,
+            ) {
+                #conflict_fields
+            }
+        }
+    }
+}
+
+/// Checks at compile time that the fields of the mirror struct exist on the remote type with the
+/// same types.
+fn expand_remote_check(container: &Container) -> TokenStream {
+    if container.attrs.remote().is_none() {
+        return TokenStream::new();
+    }
+
+    let (impl_generics, _, where_clause) = container.generics.split_for_impl();
+    let base_ty = expand_base_ty(container);
+
+    let checks = container.fields.iter().map(|field| {
+        let member = &field.member;
+        let ty = &field.ty;
+        quote_spanned! {ty.span()=>
+            let _: &#ty = &value.#member;
+        }
+    });
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code, deprecated)]
+            fn check #impl_generics (value: &#base_ty) #where_clause {
+                #(#checks)*
+            }
+        };
+    }
+}
+
+/// Returns the generics of the container with a bound for each field whose type uses a type
+/// parameter.
+fn with_field_bounds(
+    container: &Container,
+    bound: impl Fn(&ast::Field) -> syn::WherePredicate,
+) -> syn::Generics {
+    let mut generics = container.generics.clone();
+    let predicates = container
+        .fields
+        .iter()
+        .filter(|field| container.uses_type_params(field.ty.to_token_stream()))
+        .map(bound);
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// The byte and the bit of each field without `with`, in a `[u8; _]` bitset, and the length of the
+/// bitset.
+fn field_bits(fields: &[ast::Field]) -> (Vec<Option<(usize, u8)>>, usize) {
+    let mut bits = 0;
+    let field_bits = fields
+        .iter()
+        .map(|field| {
+            field.attrs.with().is_none().then(|| {
+                let bit = bits;
+                bits += 1;
+                (bit / 8, 1 << (bit % 8))
+            })
+        })
+        .collect();
+    (field_bits, bits.div_ceil(8))
+}
+
+/// The name of the field of the mask struct for a field with `with`: its identifier, or its index
+/// after an underscore.
+fn mask_field_name(field: &ast::Field) -> Ident {
+    match &field.member {
+        syn::Member::Named(ident) => ident.clone(),
+        syn::Member::Unnamed(index) => format_ident!("_{}", index.index),
+    }
+}
+
+/// The name of a field in a `FieldPath`: its identifier or its index.
+fn field_name(field: &ast::Field) -> String {
+    match &field.member {
+        syn::Member::Named(ident) => ident.unraw().to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// The type that is patched: the target struct or the type set with `#[patch(remote = ...)]`.
+fn expand_base_ty(container: &Container) -> TokenStream {
+    let (_, ty_generics, _) = container.generics.split_for_impl();
+
+    match container.attrs.remote() {
+        Some(remote) if remote.segments.last().unwrap().arguments.is_none() => {
+            quote!(#remote #ty_generics)
+        }
+        Some(remote) => quote!(#remote),
+        None => {
+            let ident = container.ident;
+            quote!(#ident #ty_generics)
+        }
+    }
+}
+
+fn expand_patch_struct(container: &Container) -> TokenStream {
+    let base_ty = expand_base_ty(container);
+    let vis = patch_struct_vis(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_struct_attributes = container.attrs.path_struct_attributes();
+    let forwarded_attributes = forwarded_struct_attributes(container);
+    let generics = container.generics;
+
+    let fields = match container.style {
+        crate::internals::ast::Style::Struct => {
+            let fields = container.fields.iter().map(|field| {
+                let name = &field.member;
+                let ty = field.attrs.patch_ty();
+                let attrs = field.attrs.path_field_attributes();
+                let forwarded_attrs = forwarded_field_attributes(container, field);
+                let vis = &field.patch_vis;
+                quote! { #(#forwarded_attrs)* #(#attrs)* #vis #name: #ty }
+            });
+            quote! {
+                { #(#fields),* }
+            }
+        }
+        crate::internals::ast::Style::Tuple => {
+            let fields = container.fields.iter().map(|field| {
+                let ty = field.attrs.patch_ty();
+                let attrs = field.attrs.path_field_attributes();
+                let forwarded_attrs = forwarded_field_attributes(container, field);
+                let vis = &field.patch_vis;
+                quote! { #(#forwarded_attrs)* #(#attrs)* #vis #ty }
+            });
+            quote! {
+                ( #(#fields),* );
+            }
+        }
+        crate::internals::ast::Style::Unit => quote!(;),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let default_fields = expand_instantiate_fields(container.style, &container.fields, |_| {
+        quote! { ::core::default::Default::default() }
+    });
+
+    let merge_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+        let name = &field.member;
+        quote! { patched::Merge::merge(self.#name, rhs.#name) }
+    });
+
+    let merge_from_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        quote! { patched::MergeFrom::merge_from(&mut self.#name, rhs.#name); }
+    });
+
+    let members = container.fields.iter().map(|field| &field.member);
+
+    // `MergeFrom` is implemented only when the fields implement it, the bounds are put behind a
+    // binder so they're not rejected when they don't hold for a non-generic type.
+    let merge_from_where_clause = {
+        let mut generics = generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(container.fields.iter().map(|field| -> syn::WherePredicate {
+                let ty = field.attrs.patch_ty();
+                parse_quote!(for<'__patched> #ty: patched::MergeFrom)
+            }));
+        generics.make_where_clause().to_token_stream()
+    };
+
+    let impl_from_trait = container.attrs.impl_from_trait().then(|| {
+        let from_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+            let name = &field.member;
+            match field.attrs.elementwise() {
+                Some(Elementwise::Tuple(len)) => {
+                    let elems = (0..len).map(syn::Index::from);
+                    quote! { ( #(::core::option::Option::Some(value.#name.#elems),)* ) }
+                }
+                Some(Elementwise::Array) => {
+                    quote! { value.#name.map(::core::option::Option::Some) }
+                }
+                None => quote! { ::core::convert::From::from(value.#name) },
+            }
+        });
+
+        quote! {
+            #[automatically_derived]
+            #[allow(deprecated)]
+            impl #impl_generics ::core::convert::From<#base_ty> for #patch_struct_name #ty_generics #where_clause {
+                #[inline]
+                fn from(value: #base_ty) -> Self {
+                    Self #from_fields
+                }
+            }
+        }
+    });
+
+    quote! {
+        #(#forwarded_attributes)*
+        #(#patch_struct_attributes)*
+        #vis struct #patch_struct_name #generics #fields
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::Merge for #patch_struct_name #ty_generics #where_clause {
+            type Output = Self;
+
+            #[inline]
+            fn merge(self, rhs: Self) -> Self::Output {
+                Self #merge_fields
+            }
+
+            #[inline]
+            fn is_empty(&self) -> bool {
+                true #(&& patched::Merge::is_empty(&self.#members))*
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::MergeFrom for #patch_struct_name #ty_generics #merge_from_where_clause {
+            #[inline]
+            fn merge_from(&mut self, rhs: Self) {
+                #merge_from_fields
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::default::Default for #patch_struct_name #ty_generics #where_clause {
+            #[inline]
+            fn default() -> Self {
+                Self #default_fields
+            }
+        }
+
+        #impl_from_trait
+    }
+}
+
+/// Implements `Subtract`, `Intersect` and `Restrict` for the patch struct with
+/// `#[patch(algebra)]`.
+fn expand_algebra_traits(container: &Container) -> TokenStream {
+    if !container.attrs.impl_algebra_traits() {
+        return TokenStream::new();
+    }
+
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let expand_impl = |trait_path: TokenStream, items: TokenStream| {
+        let generics = with_field_bounds(container, |field| {
+            let patch_ty = field.attrs.patch_ty();
+            parse_quote!(#patch_ty: #trait_path)
+        });
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        quote! {
+            #[automatically_derived]
+            #[allow(deprecated)]
+            impl #impl_generics #trait_path for #patch_struct_name #ty_generics #where_clause {
+                #items
+            }
+        }
+    };
+
+    // A compact patch struct implements `Drop`, so the patches of its fields are taken and
+    // replaced in `this`, through the accessors for the fields without `with`, whose values are
+    // kept if `keep` is true.
+    let expand_body = |op: TokenStream, keep: &dyn Fn(&ast::Field) -> TokenStream| {
+        if container.attrs.compact() {
+            let statements = expand_field_statements(&container.fields, |field| {
+                let member = &field.member;
+                match field.attrs.with() {
+                    Some(_) => quote! {
+                        this.#member = #op(::core::mem::take(&mut this.#member), &rhs.#member);
+                    },
+                    None => {
+                        let take = accessor_name(field, "take");
+                        let keep = keep(field);
+                        quote! {
+                            if !(#keep) {
+                                this.#take();
+                            }
+                        }
+                    }
+                }
+            });
+            quote! {
+                let mut this = self;
+                #statements
+                this
+            }
+        } else {
+            let fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+                let member = &field.member;
+                quote! { #op(self.#member, &rhs.#member) }
+            });
+            quote!(Self #fields)
+        }
+    };
+
+    let subtract_body = expand_body(quote!(patched::Subtract::subtract), &|field| {
+        let get = accessor_name(field, "get");
+        quote!(rhs.#get().is_none())
+    });
+
+    let intersect_body = expand_body(quote!(patched::Intersect::intersect), &|field| {
+        let get = accessor_name(field, "get");
+        quote!(rhs.#get().is_some())
+    });
+
+    let restrict_fields = expand_instantiate_fields(container.style, &container.fields, |field| {
+        let member = &field.member;
+        let name = field_name(field);
+        quote! {
+            {
+                path.push(#name);
+                let patch = patched::Restrict::restrict_at(self.#member, allowed, path);
+                path.pop();
+                patch
+            }
+        }
+    });
+    let restrict_body = if container.attrs.compact() {
+        let statements = expand_field_statements(&container.fields, |field| {
+            let member = &field.member;
+            let name = field_name(field);
+            let restrict = match field.attrs.with() {
+                Some(_) => quote! {
+                    this.#member = patched::Restrict::restrict_at(
+                        ::core::mem::take(&mut this.#member),
+                        allowed,
+                        path,
+                    );
+                },
+                None => {
+                    let set = accessor_name(field, "set");
+                    let take = accessor_name(field, "take");
+                    quote! {
+                        let value = this.#take();
+                        this.#set(patched::Restrict::restrict_at(value, allowed, path));
+                    }
+                }
+            };
+            quote! {
+                {
+                    path.push(#name);
+                    #restrict
+                    path.pop();
+                }
+            }
+        });
+        quote! {
+            let mut this = self;
+            #statements
+            this
+        }
+    } else {
+        quote!(Self #restrict_fields)
+    };
+
+    let impl_subtract_trait = expand_impl(
+        quote!(patched::Subtract),
+        quote! {
+            #[inline]
+            fn subtract(self, rhs: &Self) -> Self {
+                #subtract_body
+            }
+        },
+    );
+
+    let impl_intersect_trait = expand_impl(
+        quote!(patched::Intersect),
+        quote! {
+            #[inline]
+            fn intersect(self, rhs: &Self) -> Self {
+                #intersect_body
+            }
+        },
+    );
+
+    let impl_restrict_trait = expand_impl(
+        quote!(patched::Restrict),
+        quote! {
+            fn restrict_at(
+                self,
+                allowed: &[patched::FieldPath],
+                path: &mut patched::FieldPath,
+            ) -> Self {
+                #restrict_body
+            }
+        },
+    );
+
+    quote! {
+        #impl_subtract_trait
+        #impl_intersect_trait
+        #impl_restrict_trait
+    }
+}
+
+/// Generates a builder-like setter for each field of the patch struct with `#[patch(setters)]`,
+/// or for each field which isn't `pub` on the patch struct.
+fn expand_setters(container: &Container) -> TokenStream {
+    let prefix = container.setter_prefix();
+    let vis = patch_struct_vis(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+
+    let setters = container
+        .fields
+        .iter()
+        .filter(|field| container.has_setter(field))
+        .map(|field| {
+            let name = &field.member;
+            let setter = field.method_name(prefix).unwrap();
+            let forwarded_attrs = field.attrs.forwarded_attributes();
+
+            let (doc, args, body) = match field.attrs.with() {
+                Some(ty) => (
+                    format!("Configures the patch of `{}` with `f`.", quote!(#name)),
+                    quote!(f: impl ::core::ops::FnOnce(#ty) -> #ty),
+                    if container.attrs.compact() {
+                        // The patch struct implements `Drop`, so its fields cannot be moved out.
+                        quote! {
+                            let patch = ::core::mem::take(&mut self.#name);
+                            self.#name = f(patch);
+                        }
+                    } else {
+                        quote!(self.#name = f(self.#name);)
+                    },
+                ),
+                None => {
+                    let ty = &field.ty;
+                    // The primitive types are taken as is, so the type of a literal is inferred.
+                    let (arg_ty, value) = if is_primitive(ty) {
+                        (quote!(#ty), quote!(::core::option::Option::Some(value)))
+                    } else {
+                        (
+                            quote!(impl ::core::convert::Into<#ty>),
+                            quote!(::core::option::Option::Some(::core::convert::Into::into(
+                                value
+                            ))),
+                        )
+                    };
+                    (
+                        format!("Sets the value of `{}`.", quote!(#name)),
+                        quote!(value: #arg_ty),
+                        if container.attrs.compact() {
+                            let set = accessor_name(field, "set");
+                            quote!(self.#set(#value);)
+                        } else {
+                            quote!(self.#name = #value;)
+                        },
+                    )
+                }
+            };
+
+            quote! {
+                #(#forwarded_attrs)*
+                #[doc = #doc]
+                #[inline]
+                #[must_use]
+                #vis fn #setter(mut self, #args) -> Self {
+                    #body
+                    self
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if setters.is_empty() {
+        return TokenStream::new();
+    }
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated, dead_code)]
+        impl #impl_generics #patch_struct_name #ty_generics #where_clause {
+            #(#setters)*
+        }
+    }
+}
+
+/// Whether `ty` is a primitive integer, float, `bool` or `char`.
+fn is_primitive(ty: &syn::Type) -> bool {
+    const PRIMITIVES: &[&str] = &[
+        "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16",
+        "u32", "u64", "u128", "usize",
+    ];
+
+    match ty {
+        syn::Type::Path(ty) if ty.qself.is_none() => ty
+            .path
+            .get_ident()
+            .is_some_and(|ident| PRIMITIVES.iter().any(|primitive| ident == primitive)),
+        syn::Type::Group(ty) => is_primitive(&ty.elem),
+        syn::Type::Paren(ty) => is_primitive(&ty.elem),
+        _ => false,
+    }
+}
+
+/// The visibility of the patch struct.
+fn patch_struct_vis<'a>(container: &'a Container) -> &'a syn::Visibility {
+    container.attrs.patch_struct_vis().unwrap_or(container.vis)
+}
+
+/// The attributes forwarded from the original struct to the patch struct.
+fn forwarded_struct_attributes(container: &Container) -> Vec<Attribute> {
+    forward_attributes(
+        container,
+        container.attrs.doc_attributes(),
+        container.attrs.forwarded_attributes(),
+        || format!("Patch for `{}`.", container.ident),
+    )
+}
+
+/// The attributes forwarded from a field of the original struct to the field of the patch struct.
+fn forwarded_field_attributes(container: &Container, field: &ast::Field) -> Vec<Attribute> {
+    forward_attributes(
+        container,
+        field.attrs.doc_attributes(),
+        field.attrs.forwarded_attributes(),
+        || match &field.member {
+            syn::Member::Named(ident) => format!("Patch for `{}::{}`.", container.ident, ident),
+            syn::Member::Unnamed(index) => {
+                format!("Patch for `{}::{}`.", container.ident, index.index)
+            }
+        },
+    )
+}
+
+fn forward_attributes(
+    container: &Container,
+    doc_attributes: &[Attribute],
+    forwarded_attributes: &[Attribute],
+    rewritten_doc: impl FnOnce() -> String,
+) -> Vec<Attribute> {
+    if !container.attrs.forward_attributes() {
+        return Vec::new();
+    }
+
+    let mut attrs = if container.attrs.rewrite_docs() {
+        let doc = rewritten_doc();
+        vec![parse_quote!(#[doc = #doc])]
+    } else {
+        doc_attributes.to_vec()
+    };
+    attrs.extend_from_slice(forwarded_attributes);
+    attrs
+}
+
+/// Generates the `get_*`, `set_*` and `take_*` accessors of the fields without `with` with
+/// `#[patch(accessors)]`, like the ones of a compact patch struct.
+fn expand_accessors(container: &Container) -> TokenStream {
+    if !container.attrs.accessors() || container.attrs.compact() {
+        return TokenStream::new();
+    }
+
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+
+    let accessors = container
+        .fields
+        .iter()
+        .filter(|field| field.attrs.with().is_none())
+        .map(|field| {
+            let name = &field.member;
+            let ty = &field.ty;
+            let vis = &field.patch_vis;
+            let forwarded_attrs = field.attrs.forwarded_attributes();
+            let get = accessor_name(field, "get");
+            let set = accessor_name(field, "set");
+            let take = accessor_name(field, "take");
+            let get_doc = format!(
+                "Returns the value of `{}` set by the patch, if any.",
+                quote!(#name)
+            );
+            let set_doc = format!("Sets or unsets the value of `{}`.", quote!(#name));
+            let take_doc = format!(
+                "Unsets the value of `{}` and returns it, if any.",
+                quote!(#name)
+            );
+
+            quote! {
+                #(#forwarded_attrs)*
+                #[doc = #get_doc]
+                #[inline]
+                #vis fn #get(&self) -> ::core::option::Option<&#ty> {
+                    self.#name.as_ref()
+                }
+
+                #(#forwarded_attrs)*
+                #[doc = #set_doc]
+                #[inline]
+                #vis fn #set(&mut self, value: ::core::option::Option<#ty>) {
+                    self.#name = value;
+                }
+
+                #(#forwarded_attrs)*
+                #[doc = #take_doc]
+                #[inline]
+                #vis fn #take(&mut self) -> ::core::option::Option<#ty> {
+                    self.#name.take()
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if accessors.is_empty() {
+        return TokenStream::new();
+    }
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated, dead_code)]
+        impl #impl_generics #patch_struct_name #ty_generics #where_clause {
+            #(#accessors)*
+        }
+    }
+}
+
+/// Generates the patch struct with `#[patch(compact)]`: the presence of the values of the fields
+/// without `with` is stored in a bitset instead of an `Option` for each field, and the values are
+/// accessed with the generated `get_*`, `set_*` and `take_*` methods.
+fn expand_compact_patch_struct(container: &Container) -> TokenStream {
+    let vis = patch_struct_vis(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_struct_attributes = container.attrs.path_struct_attributes();
+    let forwarded_attributes = forwarded_struct_attributes(container);
+    let generics = container.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (field_bits, present_len) = field_bits(&container.fields);
+
+    let fields = container.fields.iter().map(|field| {
+        let name = &field.member;
+        let attrs = field.attrs.path_field_attributes();
+        let forwarded_attrs = forwarded_field_attributes(container, field);
+        match field.attrs.with() {
+            Some(_) => {
+                let ty = field.attrs.patch_ty();
+                let vis = &field.patch_vis;
+                quote! { #(#forwarded_attrs)* #(#attrs)* #vis #name: #ty }
+            }
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    #(#forwarded_attrs)* #(#attrs)*
+                    #name: ::core::mem::MaybeUninit<#ty>
+                }
+            }
+        }
+    });
+
+    let accessors = container
+        .fields
+        .iter()
+        .zip(&field_bits)
+        .filter_map(|(field, bit)| {
+            let (index, mask) = (*bit)?;
+            let name = &field.member;
+            let ty = &field.ty;
+            let vis = &field.patch_vis;
+            let forwarded_attrs = field.attrs.forwarded_attributes();
+            let get = accessor_name(field, "get");
+            let set = accessor_name(field, "set");
+            let take = accessor_name(field, "take");
+            let get_doc = format!(
+                "Returns the value of `{}` set by the patch, if any.",
+                quote!(#name)
+            );
+            let set_doc = format!("Sets or unsets the value of `{}`.", quote!(#name));
+            let take_doc = format!(
+                "Unsets the value of `{}` and returns it, if any.",
+                quote!(#name)
+            );
+
+            Some(quote! {
+                #(#forwarded_attrs)*
+                #[doc = #get_doc]
+                #[inline]
+                #vis fn #get(&self) -> ::core::option::Option<&#ty> {
+                    if self.__patched_present[#index] & #mask != 0 {
+                        // SAFETY: the bit of the field is set only while the field is initialized.
+                        ::core::option::Option::Some(unsafe { self.#name.assume_init_ref() })
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+
+                #(#forwarded_attrs)*
+                #[doc = #set_doc]
+                #[inline]
+                #vis fn #set(&mut self, value: ::core::option::Option<#ty>) {
+                    ::core::mem::drop(self.#take());
+                    if let ::core::option::Option::Some(value) = value {
+                        self.#name = ::core::mem::MaybeUninit::new(value);
+                        self.__patched_present[#index] |= #mask;
+                    }
+                }
+
+                #(#forwarded_attrs)*
+                #[doc = #take_doc]
+                #[inline]
+                #vis fn #take(&mut self) -> ::core::option::Option<#ty> {
+                    if self.__patched_present[#index] & #mask != 0 {
+                        self.__patched_present[#index] &= !#mask;
+                        // SAFETY: the bit of the field was set, so the field is initialized, and it's
+                        // now unset so the value is not read again.
+                        ::core::option::Option::Some(unsafe { self.#name.assume_init_read() })
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+            })
+        });
+
+    let default_fields = container.fields.iter().map(|field| {
+        let name = &field.member;
+        match field.attrs.with() {
+            Some(_) => quote! { #name: ::core::default::Default::default() },
+            None => quote! { #name: ::core::mem::MaybeUninit::uninit() },
+        }
+    });
+
+    let drop_fields = expand_field_statements(&container.fields, |field| {
+        if field.attrs.with().is_some() {
+            return TokenStream::new();
+        }
+        let take = accessor_name(field, "take");
+        quote! { ::core::mem::drop(self.#take()); }
+    });
+
+    let merge_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        match field.attrs.with() {
+            Some(_) => quote! {
+                self.#name = patched::Merge::merge(
+                    ::core::mem::take(&mut self.#name),
+                    ::core::mem::take(&mut rhs.#name),
+                );
+            },
+            None => {
+                let set = accessor_name(field, "set");
+                let take = accessor_name(field, "take");
+                quote! {
+                    if let value @ ::core::option::Option::Some(_) = rhs.#take() {
+                        self.#set(value);
+                    }
+                }
+            }
+        }
+    });
+
+    let merge_from_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        match field.attrs.with() {
+            Some(_) => quote! {
+                patched::MergeFrom::merge_from(&mut self.#name, ::core::mem::take(&mut rhs.#name));
+            },
+            None => {
+                let set = accessor_name(field, "set");
+                let take = accessor_name(field, "take");
+                quote! {
+                    if let value @ ::core::option::Option::Some(_) = rhs.#take() {
+                        self.#set(value);
+                    }
+                }
+            }
+        }
+    });
+
+    let clone_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        match field.attrs.with() {
+            Some(_) => quote! { patch.#name = ::core::clone::Clone::clone(&self.#name); },
+            None => {
+                let get = accessor_name(field, "get");
+                let set = accessor_name(field, "set");
+                quote! {
+                    patch.#set(::core::option::Option::map(self.#get(), ::core::clone::Clone::clone));
+                }
+            }
+        }
+    });
+
+    let debug_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        let field_name = field_name(field);
+        match field.attrs.with() {
+            Some(_) => quote! { debug.field(#field_name, &self.#name); },
+            None => {
+                let get = accessor_name(field, "get");
+                quote! { debug.field(#field_name, &self.#get()); }
+            }
+        }
+    });
+
+    let eq_fields = expand_field_statements(&container.fields, |field| {
+        let name = &field.member;
+        let value = match field.attrs.with() {
+            Some(_) => quote!(self.#name != other.#name),
+            None => {
+                let get = accessor_name(field, "get");
+                quote!(self.#get() != other.#get())
+            }
+        };
+        quote! {
+            if #value {
+                return false;
+            }
+        }
+    });
+
+    // `MergeFrom` and the traits derived with `#[patch_attr(derive(...))]` for the default layout
+    // are implemented when the stored values implement them, the bounds are put behind a binder
+    // like for the default layout.
+    let where_fields_impl = |trait_path: TokenStream, with_only: bool| {
+        let mut generics = generics.clone();
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(container.fields.iter().filter_map(|field| {
+                let ty = match field.attrs.with() {
+                    Some(_) => field.attrs.patch_ty(),
+                    None if with_only => return None,
+                    None => &field.ty,
+                };
+                Some::<syn::WherePredicate>(parse_quote!(for<'__patched> #ty: #trait_path))
+            }));
+        generics.make_where_clause().to_token_stream()
+    };
+    let merge_from_where_clause = where_fields_impl(quote!(patched::MergeFrom), true);
+    let clone_where_clause = where_fields_impl(quote!(::core::clone::Clone), false);
+    let debug_where_clause = where_fields_impl(quote!(::core::fmt::Debug), false);
+    let eq_where_clause = where_fields_impl(quote!(::core::cmp::PartialEq), false);
+    let total_eq_where_clause = where_fields_impl(quote!(::core::cmp::Eq), false);
+    let debug_name = patch_struct_name.to_string();
+    let with_members = container
+        .fields
+        .iter()
+        .filter(|field| field.attrs.with().is_some())
+        .map(|field| &field.member);
+
+    quote! {
+        #(#forwarded_attributes)*
+        #(#patch_struct_attributes)*
+        #vis struct #patch_struct_name #generics #where_clause {
+            __patched_present: [u8; #present_len],
+            #(#fields),*
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated, dead_code)]
+        impl #impl_generics #patch_struct_name #ty_generics #where_clause {
+            #(#accessors)*
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::ops::Drop for #patch_struct_name #ty_generics #where_clause {
+            #[inline]
+            fn drop(&mut self) {
+                #drop_fields
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::default::Default for #patch_struct_name #ty_generics #where_clause {
+            #[inline]
+            fn default() -> Self {
+                Self {
+                    __patched_present: [0; #present_len],
+                    #(#default_fields),*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::Merge for #patch_struct_name #ty_generics #where_clause {
+            type Output = Self;
+
+            #[inline]
+            #[allow(unused_mut)]
+            fn merge(mut self, mut rhs: Self) -> Self::Output {
+                #merge_fields
+                self
+            }
+
+            #[inline]
+            fn is_empty(&self) -> bool {
+                self.__patched_present == [0; #present_len]
+                    #(&& patched::Merge::is_empty(&self.#with_members))*
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics patched::MergeFrom for #patch_struct_name #ty_generics #merge_from_where_clause {
+            #[inline]
+            #[allow(unused_mut)]
+            fn merge_from(&mut self, mut rhs: Self) {
+                #merge_from_fields
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::clone::Clone for #patch_struct_name #ty_generics #clone_where_clause {
+            #[inline]
+            fn clone(&self) -> Self {
+                let mut patch = <Self as ::core::default::Default>::default();
+                #clone_fields
+                patch
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::fmt::Debug for #patch_struct_name #ty_generics #debug_where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                let mut debug = f.debug_struct(#debug_name);
+                #debug_fields
+                debug.finish()
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics ::core::cmp::PartialEq for #patch_struct_name #ty_generics #eq_where_clause {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                #eq_fields
+                true
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::core::cmp::Eq for #patch_struct_name #ty_generics #total_eq_where_clause {}
+    }
+}
+
+/// The name of the `get_*`, `set_*` or `take_*` accessor of a field.
+fn accessor_name(field: &ast::Field, prefix: &str) -> syn::Ident {
+    field
+        .method_name(&format!("{}_", prefix))
+        .expect("the accessors require named fields")
+}
+
+/// Moves the patch of a field out of a compact patch struct.
+fn expand_compact_take(field: &ast::Field, patch: TokenStream) -> TokenStream {
+    let member = &field.member;
+    match field.attrs.with() {
+        Some(_) => quote!(::core::mem::take(&mut #patch.#member)),
+        None => {
+            let take = accessor_name(field, "take");
+            quote!(#patch.#take())
+        }
+    }
+}
+
+/// Generates a statement for each field.
+fn expand_field_statements(
+    fields: &[ast::Field],
+    field_statement: impl FnMut(&ast::Field) -> TokenStream,
+) -> TokenStream {
+    let statements = fields.iter().map(field_statement);
+
+    quote!( #(#statements)* )
+}
+
+/// Like `expand_field_statements`, with the name of the field pushed to the `path` variable during
+/// the statement.
+fn expand_field_path_statements(
+    fields: &[ast::Field],
+    mut field_statement: impl FnMut(&ast::Field) -> TokenStream,
+) -> TokenStream {
+    expand_field_statements(fields, |field| {
+        let name = field_name(field);
+        let statement = field_statement(field);
+        quote! {
+            {
+                path.push(#name);
+                #statement
+                path.pop();
+            }
+        }
+    })
+}
+
+fn expand_instantiate_fields(
+    style: ast::Style,
+    fields: &[ast::Field],
+    mut field_value: impl FnMut(&ast::Field) -> TokenStream,
 ) -> TokenStream {
     match style {
         ast::Style::Struct => {
@@ -160,3 +2091,531 @@ fn expand_instantiate_fields(
         ast::Style::Unit => quote!(),
     }
 }
+
+#[cfg(feature = "schemars")]
+fn expand_json_schema(container: &Container) -> TokenStream {
+    if !container.attrs.impl_json_schema() {
+        return TokenStream::new();
+    }
+
+    let schemars = quote!(patched::__private::schemars);
+
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let patch_struct_name_str = patch_struct_name.to_string();
+
+    let mut generics = container.generics.clone();
+    let type_params = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(#schemars::JsonSchema));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = container.generics.split_for_impl();
+
+    let (schema_name, schema_id) = if type_params.is_empty() {
+        (
+            quote!(#patch_struct_name_str),
+            quote!(::core::concat!(::core::module_path!(), "::", #patch_struct_name_str)),
+        )
+    } else {
+        let names_fmt = vec!["{}"; type_params.len()].join("_and_");
+        let ids_fmt = vec!["{}"; type_params.len()].join(", ");
+        (
+            quote! {
+                ::std::format!(
+                    ::core::concat!(#patch_struct_name_str, "_for_", #names_fmt),
+                    #(<#type_params as #schemars::JsonSchema>::schema_name()),*
+                )
+            },
+            quote! {
+                ::std::format!(
+                    ::core::concat!(::core::module_path!(), "::", #patch_struct_name_str, "<", #ids_fmt, ">"),
+                    #(<#type_params as #schemars::JsonSchema>::schema_id()),*
+                )
+            },
+        )
+    };
+
+    let description = match container.attrs.doc() {
+        Some(doc) => quote!(::core::option::Option::Some(#doc)),
+        None => quote!(::core::option::Option::None),
+    };
+
+    let schema = match container.style {
+        ast::Style::Struct => {
+            let properties = container.fields.iter().map(|field| {
+                let name = match &field.member {
+                    syn::Member::Named(ident) => ident.to_string(),
+                    syn::Member::Unnamed(index) => index.index.to_string(),
+                };
+                // Every property is optional, with the schema of the field of the patch struct.
+                let ty = field.attrs.patch_ty();
+                let description = match field.attrs.doc() {
+                    Some(doc) => quote!(::core::option::Option::Some(#doc)),
+                    None => quote!(::core::option::Option::None),
+                };
+                quote! {
+                    (#name, generator.subschema_for::<#ty>(), #description)
+                }
+            });
+            quote! {
+                #schemars::object_schema(#description, [#(#properties),*])
+            }
+        }
+        ast::Style::Tuple => {
+            let items = container.fields.iter().map(|field| {
+                let ty = field.attrs.patch_ty();
+                quote! { generator.subschema_for::<#ty>() }
+            });
+            quote! {
+                #schemars::tuple_schema(#description, [#(#items),*])
+            }
+        }
+        ast::Style::Unit => quote! {
+            #schemars::unit_schema(#description)
+        },
+    };
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #schemars::JsonSchema for #patch_struct_name #ty_generics #where_clause {
+            fn schema_name() -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::from(#schema_name)
+            }
+
+            fn schema_id() -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::from(#schema_id)
+            }
+
+            fn json_schema(generator: &mut #schemars::SchemaGenerator) -> #schemars::Schema {
+                #schema
+            }
+        }
+    }
+}
+
+/// Implements `Wire` and `WireField` for the patch struct with the `wire` feature.
+#[cfg(feature = "wire")]
+fn expand_wire_traits(container: &Container) -> TokenStream {
+    let wire = quote!(patched::wire);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let count = container.fields.len();
+    let compact_value =
+        |field: &ast::Field| container.attrs.compact() && field.attrs.with().is_none();
+
+    // The traits are implemented only when the fields implement them, the bounds are put behind a
+    // binder like for `MergeFrom`.
+    let mut generics = container.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(container.fields.iter().map(|field| -> syn::WherePredicate {
+            if compact_value(field) {
+                let ty = &field.ty;
+                parse_quote!(for<'__patched> #ty: #wire::Wire)
+            } else {
+                let ty = field.attrs.patch_ty();
+                parse_quote!(for<'__patched> #ty: #wire::WireField)
+            }
+        }));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let encode_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        if compact_value(field) {
+            let get = accessor_name(field, "get");
+            quote! { fields.value(self.#get()); }
+        } else {
+            quote! { fields.field(&self.#member); }
+        }
+    });
+
+    let decode_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        if compact_value(field) {
+            let set = accessor_name(field, "set");
+            quote! { patch.#set(fields.value()?); }
+        } else {
+            quote! { patch.#member = fields.field()?; }
+        }
+    });
+
+    let is_set_fields = expand_field_statements(&container.fields, |field| {
+        let member = &field.member;
+        let is_set = if compact_value(field) {
+            let get = accessor_name(field, "get");
+            quote!(self.#get().is_some())
+        } else {
+            quote!(#wire::WireField::is_set(&self.#member))
+        };
+        quote! {
+            if #is_set {
+                return true;
+            }
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #wire::Wire for #patch_struct_name #ty_generics #where_clause {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                let mut fields = #wire::FieldsEncoder::new(out, #count);
+                #encode_fields
+                fields.finish();
+            }
+
+            #[allow(unused_mut)]
+            fn decode(input: &mut &[u8]) -> ::core::result::Result<Self, #wire::DecodeError> {
+                let mut fields = #wire::FieldsDecoder::new(input)?;
+                let mut patch = <Self as ::core::default::Default>::default();
+                #decode_fields
+                fields.finish()?;
+                ::core::result::Result::Ok(patch)
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #wire::WireField for #patch_struct_name #ty_generics #where_clause {
+            /// The patch is set if one of its fields is set.
+            fn is_set(&self) -> bool {
+                #is_set_fields
+                false
+            }
+
+            #[inline]
+            fn encode_set(&self, out: &mut ::std::vec::Vec<u8>) {
+                #wire::Wire::encode(self, out);
+            }
+
+            #[inline]
+            fn decode_set(input: &mut &[u8]) -> ::core::result::Result<Self, #wire::DecodeError> {
+                #wire::Wire::decode(input)
+            }
+
+            #[inline]
+            fn unset() -> Self {
+                ::core::default::Default::default()
+            }
+        }
+    }
+}
+
+/// Implements `DeserializePatch` for the target struct with `#[patch(deserialize_patch)]`,
+/// following the `serde` format of the patch struct.
+#[cfg(feature = "serde")]
+fn expand_deserialize_patch_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_deserialize_patch() {
+        return TokenStream::new();
+    }
+
+    let serde = quote!(patched::serde);
+    let de = quote!(patched::__private::serde);
+    let base_ty = expand_base_ty(container);
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let serde_attrs = container.attrs.serde();
+    let name = match serde_attrs.rename() {
+        Some(rename) => rename.to_owned(),
+        None => patch_struct_name.to_string(),
+    };
+    let deny_unknown_fields = serde_attrs.deny_unknown_fields();
+
+    let fields = container
+        .fields
+        .iter()
+        .filter(|field| !field.attrs.serde().skip())
+        .collect::<Vec<_>>();
+    let field_keys = fields
+        .iter()
+        .map(|field| match field.attrs.serde().rename() {
+            Some(rename) => rename.to_owned(),
+            None => serde_attrs.rename_all().apply_to_field(&field_name(field)),
+        })
+        .collect::<Vec<_>>();
+
+    // Every field must be deserialized like the field of the patch struct, the bounds are not
+    // hidden so that a field which can't be is an error.
+    let mut generics = container.generics.clone();
+    generics.params.insert(0, parse_quote!('de));
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(fields.iter().flat_map(|field| -> Vec<syn::WherePredicate> {
+            let ty = &field.ty;
+            let patch_ty = field.attrs.patch_ty();
+            if field.attrs.elementwise().is_some() || field.attrs.option_with() {
+                vec![
+                    parse_quote!(#patch_ty: #de::Deserialize<'de>),
+                    parse_quote!(#ty: patched::Patch<#patch_ty>),
+                ]
+            } else if field.attrs.with().is_some() {
+                vec![parse_quote!(#ty: #serde::DeserializePatch<'de, Patch = #patch_ty>)]
+            } else {
+                vec![parse_quote!(#ty: #de::Deserialize<'de>)]
+            }
+        }));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = container.generics.split_for_impl();
+
+    // The bounds depend on `'de` so a false one removes the impl instead of being an error, the
+    // fields which don't use the generics of the container are checked on their own.
+    let checks = fields
+        .iter()
+        .filter(|field| {
+            container.generics.params.is_empty()
+                || !container.uses_type_params(field.ty.to_token_stream())
+                    && container.generics.lifetimes().next().is_none()
+        })
+        .map(|field| {
+            let ty = &field.ty;
+            let patch_ty = field.attrs.patch_ty();
+            if field.attrs.elementwise().is_some() || field.attrs.option_with() {
+                quote_spanned! {ty.span()=>
+                    deserialize::<#patch_ty>();
+                }
+            } else if field.attrs.with().is_some() {
+                quote_spanned! {ty.span()=>
+                    deserialize_patch::<#ty, #patch_ty>();
+                }
+            } else {
+                quote_spanned! {ty.span()=>
+                    deserialize::<#ty>();
+                }
+            }
+        });
+
+    let default_fields = fields
+        .iter()
+        .map(|field| serde_attrs.has_default() || field.attrs.serde().has_default());
+
+    let index_arms = fields
+        .iter()
+        .zip(&field_keys)
+        .enumerate()
+        .map(|(index, (field, key))| {
+            let aliases = field.attrs.serde().aliases();
+            quote! {
+                #key #(| #aliases)* => ::core::option::Option::Some(#index),
+            }
+        });
+
+    let field_arms = fields.iter().enumerate().map(|(index, field)| {
+        let member = &field.member;
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        let statement = if field.attrs.elementwise().is_some() || field.attrs.option_with() {
+            quote! {
+                patched::Patch::patch(
+                    &mut self.#member,
+                    #de::MapAccess::next_value::<#patch_ty>(map)?,
+                );
+            }
+        } else if field.attrs.with().is_some() {
+            quote! {
+                #de::MapAccess::next_value_seed(
+                    map,
+                    #serde::PatchSeed(&mut self.#member),
+                )?;
+            }
+        } else {
+            // A `null` is a `None` field of the patch struct, which leaves the field untouched.
+            quote! {
+                if let ::core::option::Option::Some(value) =
+                    #de::MapAccess::next_value::<::core::option::Option<#ty>>(map)?
+                {
+                    self.#member = value;
+                }
+            }
+        };
+        quote! {
+            #index => {
+                #statement
+            }
+        }
+    });
+
+    // The same as `field_arms`, for the next element of a sequence.
+    let element_arms = fields.iter().enumerate().map(|(index, field)| {
+        let member = &field.member;
+        let ty = &field.ty;
+        let patch_ty = field.attrs.patch_ty();
+        let statement = if field.attrs.elementwise().is_some() || field.attrs.option_with() {
+            quote! {
+                match #de::SeqAccess::next_element::<#patch_ty>(seq)? {
+                    ::core::option::Option::Some(patch) => {
+                        patched::Patch::patch(&mut self.#member, patch);
+                    }
+                    ::core::option::Option::None => return ::core::result::Result::Ok(false),
+                }
+            }
+        } else if field.attrs.with().is_some() {
+            quote! {
+                let element = #de::SeqAccess::next_element_seed(
+                    seq,
+                    #serde::PatchSeed(&mut self.#member),
+                )?;
+                if element.is_none() {
+                    return ::core::result::Result::Ok(false);
+                }
+            }
+        } else {
+            quote! {
+                match #de::SeqAccess::next_element::<::core::option::Option<#ty>>(seq)? {
+                    ::core::option::Option::Some(value) => {
+                        if let ::core::option::Option::Some(value) = value {
+                            self.#member = value;
+                        }
+                    }
+                    ::core::option::Option::None => return ::core::result::Result::Ok(false),
+                }
+            }
+        };
+        quote! {
+            #index => {
+                #statement
+            }
+        }
+    });
+
+    quote! {
+        const _: () = {
+            fn deserialize<T: for<'de> #de::Deserialize<'de>>() {}
+            fn deserialize_patch<T, P>()
+            where
+                T: for<'de> #serde::DeserializePatch<'de, Patch = P> + ?::core::marker::Sized,
+            {
+            }
+
+            #[allow(dead_code, deprecated)]
+            fn check() {
+                #(#checks)*
+            }
+        };
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #serde::PatchFields<'de> for #base_ty #where_clause {
+            const NAME: &'static str = #name;
+            const FIELDS: &'static [&'static str] = &[#(#field_keys),*];
+            const DENY_UNKNOWN_FIELDS: bool = #deny_unknown_fields;
+            const DEFAULT_FIELDS: &'static [bool] = &[#(#default_fields),*];
+
+            fn field_index(name: &str) -> ::core::option::Option<usize> {
+                match name {
+                    #(#index_arms)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            fn patch_field<__A>(
+                &mut self,
+                index: usize,
+                map: &mut __A,
+            ) -> ::core::result::Result<(), __A::Error>
+            where
+                __A: #de::MapAccess<'de>,
+            {
+                match index {
+                    #(#field_arms)*
+                    _ => {
+                        #de::MapAccess::next_value::<#de::IgnoredAny>(map)?;
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            fn patch_element<__A>(
+                &mut self,
+                index: usize,
+                seq: &mut __A,
+            ) -> ::core::result::Result<bool, __A::Error>
+            where
+                __A: #de::SeqAccess<'de>,
+            {
+                match index {
+                    #(#element_arms)*
+                    _ => {}
+                }
+                ::core::result::Result::Ok(true)
+            }
+        }
+
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #serde::DeserializePatch<'de> for #base_ty #where_clause {
+            type Patch = #patch_struct_name #ty_generics;
+
+            #[inline]
+            fn deserialize_patch<__D>(
+                &mut self,
+                deserializer: __D,
+            ) -> ::core::result::Result<(), __D::Error>
+            where
+                __D: #de::Deserializer<'de>,
+            {
+                #serde::deserialize_fields(self, deserializer)
+            }
+        }
+    }
+}
+
+/// Implements `proptest::arbitrary::Arbitrary` for the patch struct with `#[patch(arbitrary)]`.
+#[cfg(feature = "proptest")]
+fn expand_arbitrary_trait(container: &Container) -> TokenStream {
+    if !container.attrs.impl_arbitrary_trait() {
+        return TokenStream::new();
+    }
+
+    let proptest = quote!(patched::__private::proptest);
+
+    let mut generics = with_field_bounds(container, |field| {
+        let patch_ty = field.attrs.patch_ty();
+        parse_quote!(#patch_ty: #proptest::arbitrary::Arbitrary)
+    });
+    let patch_struct_name = container.attrs.patch_struct_name();
+    let (_, ty_generics, _) = container.generics.split_for_impl();
+    generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(#patch_struct_name #ty_generics: 'static));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // Each field is generated by its own strategy, combined with the strategy of the previous
+    // fields so there is no limit on the number of fields.
+    let strategy_fields = container.fields.iter().map(|field| {
+        let member = &field.member;
+        let patch_ty = field.attrs.patch_ty();
+        quote! {
+            let strategy = #proptest::strategy::Strategy::boxed(
+                #proptest::strategy::Strategy::prop_map(
+                    (strategy, #proptest::arbitrary::any::<#patch_ty>()),
+                    |(mut patch, value)| {
+                        patch.#member = value;
+                        patch
+                    },
+                ),
+            );
+        }
+    });
+
+    quote! {
+        #[automatically_derived]
+        #[allow(deprecated)]
+        impl #impl_generics #proptest::arbitrary::Arbitrary for #patch_struct_name #ty_generics #where_clause {
+            type Parameters = ();
+            type Strategy = #proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                let strategy = #proptest::strategy::Strategy::boxed(
+                    #proptest::strategy::LazyJust::new(<Self as ::core::default::Default>::default),
+                );
+                #(#strategy_fields)*
+                strategy
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
-use syn::{punctuated::Punctuated, Generics, Ident, Member, Token, Visibility};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::format_ident;
+use syn::{
+    ext::IdentExt, parse_quote, punctuated::Punctuated, Generics, Ident, Member, Token, Type,
+    Visibility,
+};
 
-use super::{attr, Context};
+use super::{attr, check, Context};
 
 pub struct Container<'a> {
     pub attrs: attr::Container,
@@ -14,6 +19,9 @@ pub struct Container<'a> {
 pub struct Field {
     pub member: Member,
     pub attrs: attr::Field,
+    pub ty: Type,
+    /// The visibility of the field on the patch struct.
+    pub patch_vis: Visibility,
 }
 
 #[derive(Copy, Clone)]
@@ -32,7 +40,7 @@ impl<'a> Container<'a> {
         let attrs = attr::Container::from_ast(cx, item);
 
         let (style, fields) = match &item.data {
-            syn::Data::Struct(data) => struct_from_ast(cx, &data.fields),
+            syn::Data::Struct(data) => struct_from_ast(cx, &attrs, &data.fields),
             syn::Data::Enum(_) => {
                 cx.error_spanned_by(item, "Patch does not support derive for enums");
                 return None;
@@ -43,35 +51,125 @@ impl<'a> Container<'a> {
             }
         };
 
-        Some(Container {
+        if attrs.compact() && !matches!(style, Style::Struct) {
+            cx.error_spanned_by(item, "`compact` requires a struct with named fields");
+        }
+        if attrs.setter_prefix().is_some() && matches!(style, Style::Tuple) {
+            cx.error_spanned_by(item, "`setters` requires a struct with named fields");
+        }
+        if attrs.accessors() && !attrs.compact() && matches!(style, Style::Tuple) {
+            cx.error_spanned_by(item, "`accessors` requires a struct with named fields");
+        }
+        if attrs.impl_deserialize_patch() && !matches!(style, Style::Struct) {
+            cx.error_spanned_by(
+                item,
+                "`deserialize_patch` requires a struct with named fields",
+            );
+        }
+
+        let container = Container {
             attrs,
             vis: &item.vis,
             ident: &item.ident,
             generics: &item.generics,
             style,
             fields,
+        };
+        check::check(cx, &container);
+        Some(container)
+    }
+
+    /// The prefix of the setters, empty without `#[patch(setters = "...")]`.
+    pub fn setter_prefix(&self) -> &str {
+        self.attrs.setter_prefix().unwrap_or_default()
+    }
+
+    /// Whether a setter is generated for the field: with `#[patch(setters)]`, or when the field of
+    /// the patch struct isn't `pub` so that the patch can be built where the field is private.
+    pub fn has_setter(&self, field: &Field) -> bool {
+        matches!(self.style, Style::Struct)
+            && (self.attrs.setter_prefix().is_some()
+                || !matches!(field.patch_vis, Visibility::Public(_)))
+    }
+
+    /// Whether `tokens`, e.g. the type of a field, use a type parameter of the container.
+    pub fn uses_type_params(&self, tokens: TokenStream) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => self
+                .generics
+                .type_params()
+                .any(|param| ident == param.ident),
+            TokenTree::Group(group) => self.uses_type_params(group.stream()),
+            _ => false,
         })
     }
 }
 
-fn struct_from_ast(cx: &Context, fields: &syn::Fields) -> (Style, Vec<Field>) {
+impl Field {
+    /// The name of a method generated for a named field: the name of the field after `prefix`.
+    pub fn method_name(&self, prefix: &str) -> Option<Ident> {
+        match &self.member {
+            Member::Named(ident) if prefix.is_empty() => Some(ident.clone()),
+            Member::Named(ident) => Some(format_ident!("{}{}", prefix, ident.unraw())),
+            Member::Unnamed(_) => None,
+        }
+    }
+
+    /// The name of the constant of the field on the mask struct: the name of the field in
+    /// uppercase, or its index after an underscore.
+    pub fn mask_const_name(&self) -> Ident {
+        match &self.member {
+            Member::Named(ident) => format_ident!("{}", ident.unraw().to_string().to_uppercase()),
+            Member::Unnamed(index) => format_ident!("_{}", index.index),
+        }
+    }
+}
+
+fn struct_from_ast(
+    cx: &Context,
+    container: &attr::Container,
+    fields: &syn::Fields,
+) -> (Style, Vec<Field>) {
     match fields {
-        syn::Fields::Named(fields) => (Style::Struct, fields_from_ast(cx, &fields.named)),
-        syn::Fields::Unnamed(fields) => (Style::Tuple, fields_from_ast(cx, &fields.unnamed)),
+        syn::Fields::Named(fields) => {
+            (Style::Struct, fields_from_ast(cx, container, &fields.named))
+        }
+        syn::Fields::Unnamed(fields) => (
+            Style::Tuple,
+            fields_from_ast(cx, container, &fields.unnamed),
+        ),
         syn::Fields::Unit => (Style::Unit, Vec::new()),
     }
 }
 
-fn fields_from_ast(cx: &Context, fields: &Punctuated<syn::Field, Token![,]>) -> Vec<Field> {
+fn fields_from_ast(
+    cx: &Context,
+    container: &attr::Container,
+    fields: &Punctuated<syn::Field, Token![,]>,
+) -> Vec<Field> {
     fields
         .iter()
         .enumerate()
-        .map(|(i, field)| Field {
-            member: match &field.ident {
-                Some(ident) => syn::Member::Named(ident.clone()),
-                None => syn::Member::Unnamed(i.into()),
-            },
-            attrs: attr::Field::from_ast(cx, field),
+        .map(|(i, field)| {
+            let attrs = attr::Field::from_ast(cx, container, field);
+
+            // The visibility of the field takes precedence over the one of the container,
+            // fields are public by default.
+            let patch_vis = match attrs.vis().or(container.field_vis()) {
+                Some(attr::FieldVis::Inherit) => field.vis.clone(),
+                Some(attr::FieldVis::Vis(vis)) => vis.clone(),
+                None => parse_quote!(pub),
+            };
+
+            Field {
+                member: match &field.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(i.into()),
+                },
+                attrs,
+                ty: field.ty.clone(),
+                patch_vis,
+            }
         })
         .collect()
 }
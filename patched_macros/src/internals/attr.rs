@@ -1,10 +1,12 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, ToTokens};
-use syn::{parse_quote, Attribute, Ident};
+use syn::{
+    meta::ParseNestedMeta, parse_quote, punctuated::Punctuated, Attribute, Ident, Token, Visibility,
+};
 
 use crate::internals::symbol::*;
 
-use super::Context;
+use super::{case::RenameRule, Context};
 
 /* -------------------------------------------------------------------------- */
 
@@ -87,15 +89,59 @@ impl<T> VecAttr<T> {
 
 pub struct Container {
     patch_struct_name: Ident,
+    remote: Option<syn::Path>,
+    patch_struct_vis: Option<Visibility>,
+    field_vis: Option<FieldVis>,
     path_struct_attributes: Vec<Attribute>,
     impl_from_trait: bool,
+    setter_prefix: Option<String>,
+    impl_expect_trait: bool,
+    impl_try_merge_trait: bool,
+    impl_patch_ref_trait: bool,
+    impl_try_from_trait: bool,
+    try_into_default: bool,
+    impl_patch_notify_trait: bool,
+    impl_minimize_trait: bool,
+    impl_algebra_traits: bool,
+    mask_struct_name: Option<Ident>,
+    compact: bool,
+    accessors: bool,
+    impl_arbitrary_trait: bool,
+    impl_json_schema: bool,
+    impl_deserialize_patch: bool,
+    serde: SerdeContainer,
+    doc_attributes: Vec<Attribute>,
+    forwarded_attributes: Vec<Attribute>,
+    forward_attributes: bool,
+    rewrite_docs: bool,
+    doc: Option<String>,
 }
 
 impl Container {
     pub fn from_ast(cx: &Context, item: &syn::DeriveInput) -> Self {
         let mut patch_struct_name = Attr::none(cx, NAME);
+        let mut remote = Attr::none(cx, REMOTE);
+        let mut patch_struct_vis = Attr::none(cx, VIS);
+        let mut field_vis = Attr::none(cx, FIELD_VIS);
         let mut path_struct_attributes = VecAttr::none();
         let mut impl_from_trait = BoolAttr::none(cx, FROM);
+        let mut setter_prefix = Attr::none(cx, SETTERS);
+        let mut impl_expect_trait = BoolAttr::none(cx, EXPECT);
+        let mut impl_try_merge_trait = BoolAttr::none(cx, TRY_MERGE);
+        let mut impl_patch_ref_trait = BoolAttr::none(cx, BY_REF);
+        let mut impl_try_from_trait = BoolAttr::none(cx, TRY_INTO);
+        let mut try_into_default = BoolAttr::none(cx, TRY_INTO_DEFAULT);
+        let mut impl_patch_notify_trait = BoolAttr::none(cx, NOTIFY);
+        let mut impl_minimize_trait = BoolAttr::none(cx, MINIMIZE);
+        let mut impl_algebra_traits = BoolAttr::none(cx, ALGEBRA);
+        let mut mask = BoolAttr::none(cx, MASK);
+        let mut compact = BoolAttr::none(cx, COMPACT);
+        let mut accessors = BoolAttr::none(cx, ACCESSORS);
+        let mut impl_arbitrary_trait = BoolAttr::none(cx, ARBITRARY);
+        let mut impl_json_schema = BoolAttr::none(cx, JSON_SCHEMA);
+        let mut impl_deserialize_patch = BoolAttr::none(cx, DESERIALIZE_PATCH);
+        let mut no_forward_attrs = BoolAttr::none(cx, NO_FORWARD_ATTRS);
+        let mut rewrite_docs = BoolAttr::none(cx, REWRITE_DOCS);
 
         for attr in &item.attrs {
             if attr.path() == PATCH_ATTR {
@@ -134,8 +180,84 @@ impl Container {
                 if meta.path == NAME {
                     // #[patch(name = Foo)]
                     patch_struct_name.set(&meta.path, meta.value()?.parse()?);
+                } else if meta.path == REMOTE {
+                    // #[patch(remote = other::Config)]
+                    remote.set(&meta.path, meta.value()?.parse()?);
+                } else if meta.path == VIS {
+                    // #[patch(vis = "pub(crate)")]
+                    patch_struct_vis.set(&meta.path, parse_lit_into_vis(&meta.value()?.parse()?)?);
+                } else if meta.path == FIELD_VIS {
+                    // #[patch(field_vis = inherit)]
+                    // #[patch(field_vis = "pub(crate)")]
+                    field_vis.set(&meta.path, parse_field_vis(&meta)?);
                 } else if meta.path == FROM {
                     impl_from_trait.set_true(&meta.path);
+                } else if meta.path == SETTERS {
+                    if meta.input.peek(syn::Token![=]) {
+                        // #[patch(setters = "with_")]
+                        let prefix: syn::LitStr = meta.value()?.parse()?;
+                        setter_prefix.set(&meta.path, prefix.value());
+                    } else {
+                        // #[patch(setters)]
+                        setter_prefix.set(&meta.path, String::new());
+                    }
+                } else if meta.path == EXPECT {
+                    // #[patch(expect)]
+                    impl_expect_trait.set_true(&meta.path);
+                } else if meta.path == TRY_MERGE {
+                    // #[patch(try_merge)]
+                    impl_try_merge_trait.set_true(&meta.path);
+                } else if meta.path == BY_REF {
+                    // #[patch(by_ref)]
+                    impl_patch_ref_trait.set_true(&meta.path);
+                } else if meta.path == TRY_INTO {
+                    // #[patch(try_into)]
+                    impl_try_from_trait.set_true(&meta.path);
+                } else if meta.path == TRY_INTO_DEFAULT {
+                    // #[patch(try_into_default)]
+                    try_into_default.set_true(&meta.path);
+                } else if meta.path == NOTIFY {
+                    // #[patch(notify)]
+                    impl_patch_notify_trait.set_true(&meta.path);
+                } else if meta.path == MINIMIZE {
+                    // #[patch(minimize)]
+                    impl_minimize_trait.set_true(&meta.path);
+                } else if meta.path == ALGEBRA {
+                    // #[patch(algebra)]
+                    impl_algebra_traits.set_true(&meta.path);
+                } else if meta.path == MASK {
+                    // #[patch(mask)]
+                    mask.set_true(&meta.path);
+                } else if meta.path == COMPACT {
+                    // #[patch(compact)]
+                    compact.set_true(&meta.path);
+                } else if meta.path == ACCESSORS {
+                    // #[patch(accessors)]
+                    accessors.set_true(&meta.path);
+                } else if meta.path == ARBITRARY {
+                    // #[patch(arbitrary)]
+                    if cfg!(not(feature = "proptest")) {
+                        return Err(meta.error("`arbitrary` requires the `proptest` feature"));
+                    }
+                    impl_arbitrary_trait.set_true(&meta.path);
+                } else if meta.path == JSON_SCHEMA {
+                    // #[patch(json_schema)]
+                    if cfg!(not(feature = "schemars")) {
+                        return Err(meta.error("`json_schema` requires the `schemars` feature"));
+                    }
+                    impl_json_schema.set_true(&meta.path);
+                } else if meta.path == DESERIALIZE_PATCH {
+                    // #[patch(deserialize_patch)]
+                    if cfg!(not(feature = "serde")) {
+                        return Err(meta.error("`deserialize_patch` requires the `serde` feature"));
+                    }
+                    impl_deserialize_patch.set_true(&meta.path);
+                } else if meta.path == NO_FORWARD_ATTRS {
+                    // #[patch(no_forward_attrs)]
+                    no_forward_attrs.set_true(&meta.path);
+                } else if meta.path == REWRITE_DOCS {
+                    // #[patch(rewrite_docs)]
+                    rewrite_docs.set_true(&meta.path);
                 } else {
                     let path = meta.path.to_token_stream().to_string().replace(' ', "");
                     return Err(
@@ -149,19 +271,146 @@ impl Container {
             }
         }
 
-        Container {
+        let remote: Option<syn::Path> = remote.get();
+        let ident = match &remote {
+            Some(remote) => &remote.segments.last().unwrap().ident,
+            None => &item.ident,
+        };
+
+        let mask = mask.get();
+
+        // The `serde` attributes of the patch struct are only read to deserialize a patch.
+        let path_struct_attributes = path_struct_attributes.get();
+        let impl_deserialize_patch = impl_deserialize_patch.get();
+        let serde = if impl_deserialize_patch {
+            if remote.is_some() {
+                cx.error_spanned_by(
+                    &item.ident,
+                    "`deserialize_patch` cannot be used with `remote`, the trait can't be \
+                     implemented for a type of another crate",
+                );
+            }
+            SerdeContainer::from_attrs(cx, &path_struct_attributes)
+        } else {
+            SerdeContainer::default()
+        };
+
+        let container = Container {
             patch_struct_name: patch_struct_name
                 .get()
-                .unwrap_or_else(|| format_ident!("{}Patch", item.ident)),
-            path_struct_attributes: path_struct_attributes.get(),
+                .unwrap_or_else(|| format_ident!("{}Patch", ident)),
+            mask_struct_name: mask.then(|| format_ident!("{}Mask", ident)),
+            remote,
+            patch_struct_vis: patch_struct_vis.get(),
+            field_vis: field_vis.get(),
+            path_struct_attributes,
             impl_from_trait: impl_from_trait.get(),
+            setter_prefix: setter_prefix.get(),
+            impl_expect_trait: impl_expect_trait.get(),
+            impl_try_merge_trait: impl_try_merge_trait.get(),
+            impl_patch_ref_trait: impl_patch_ref_trait.get(),
+            impl_try_from_trait: impl_try_from_trait.get(),
+            try_into_default: try_into_default.get(),
+            impl_patch_notify_trait: impl_patch_notify_trait.get(),
+            impl_minimize_trait: impl_minimize_trait.get(),
+            impl_algebra_traits: impl_algebra_traits.get(),
+            impl_arbitrary_trait: impl_arbitrary_trait.get(),
+            impl_json_schema: impl_json_schema.get(),
+            impl_deserialize_patch,
+            serde,
+            doc_attributes: get_doc_attributes(&item.attrs),
+            forwarded_attributes: get_forwarded_attributes(&item.attrs),
+            forward_attributes: !no_forward_attrs.get(),
+            rewrite_docs: rewrite_docs.get(),
+            doc: get_doc(&item.attrs),
+            compact: compact.get(),
+            accessors: accessors.get(),
+        };
+
+        if container.try_into_default && !container.impl_try_from_trait {
+            cx.error_spanned_by(&item.ident, "`try_into_default` requires `try_into`");
+        }
+
+        if container.compact {
+            // These traits access the fields of the patch struct, which are private.
+            let incompatible = [
+                (container.impl_from_trait, FROM),
+                (container.impl_expect_trait, EXPECT),
+                (container.impl_try_merge_trait, TRY_MERGE),
+                (container.impl_patch_ref_trait, BY_REF),
+                (container.impl_try_from_trait, TRY_INTO),
+                (container.impl_patch_notify_trait, NOTIFY),
+                (container.impl_minimize_trait, MINIMIZE),
+                (container.mask_struct_name.is_some(), MASK),
+                (container.impl_arbitrary_trait, ARBITRARY),
+                (container.impl_json_schema, JSON_SCHEMA),
+            ];
+            for (_, symbol) in incompatible.iter().filter(|(set, _)| *set) {
+                cx.error_spanned_by(
+                    &item.ident,
+                    format_args!("`compact` cannot be used with `{}`", symbol),
+                );
+            }
+        }
+
+        // The patch struct implements `Default`, and these traits when it's compact.
+        let implemented: &[&str] = if container.compact {
+            &["Default", "Clone", "Debug", "PartialEq", "Eq"]
+        } else {
+            &["Default"]
+        };
+        for attr in &container.path_struct_attributes {
+            let syn::Meta::List(meta) = &attr.meta else {
+                continue;
+            };
+            if meta.path != DERIVE {
+                continue;
+            }
+            let paths =
+                match meta.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated) {
+                    Ok(paths) => paths,
+                    Err(error) => {
+                        cx.syn_error(error);
+                        continue;
+                    }
+                };
+            for path in paths {
+                let ident = &path.segments.last().unwrap().ident;
+                if let Some(name) = implemented.iter().find(|name| ident == *name) {
+                    cx.error_spanned_by(
+                        &path,
+                        format_args!(
+                            "the patch struct already implements `{}`, it must not be derived \
+                             with `patch_attr`",
+                            name,
+                        ),
+                    );
+                }
+            }
         }
+
+        container
     }
 
     pub fn patch_struct_name(&self) -> &Ident {
         &self.patch_struct_name
     }
 
+    /// The type set with `#[patch(remote = ...)]`, if any.
+    pub fn remote(&self) -> Option<&syn::Path> {
+        self.remote.as_ref()
+    }
+
+    /// The visibility set with `#[patch(vis = "...")]`, if any.
+    pub fn patch_struct_vis(&self) -> Option<&Visibility> {
+        self.patch_struct_vis.as_ref()
+    }
+
+    /// The visibility set with `#[patch(field_vis = ...)]`, if any.
+    pub(crate) fn field_vis(&self) -> Option<&FieldVis> {
+        self.field_vis.as_ref()
+    }
+
     pub fn path_struct_attributes(&self) -> &[Attribute] {
         &self.path_struct_attributes
     }
@@ -169,18 +418,134 @@ impl Container {
     pub fn impl_from_trait(&self) -> bool {
         self.impl_from_trait
     }
+
+    /// The prefix of the setters generated with `#[patch(setters)]`, if any.
+    pub fn setter_prefix(&self) -> Option<&str> {
+        self.setter_prefix.as_deref()
+    }
+
+    pub fn impl_expect_trait(&self) -> bool {
+        self.impl_expect_trait
+    }
+
+    pub fn impl_try_merge_trait(&self) -> bool {
+        self.impl_try_merge_trait
+    }
+
+    pub fn impl_patch_ref_trait(&self) -> bool {
+        self.impl_patch_ref_trait
+    }
+
+    pub fn impl_try_from_trait(&self) -> bool {
+        self.impl_try_from_trait
+    }
+
+    /// Whether the missing fields are taken from the `Default` value of the struct when it's
+    /// built from a patch.
+    pub fn try_into_default(&self) -> bool {
+        self.try_into_default
+    }
+
+    pub fn impl_patch_notify_trait(&self) -> bool {
+        self.impl_patch_notify_trait
+    }
+
+    pub fn impl_minimize_trait(&self) -> bool {
+        self.impl_minimize_trait
+    }
+
+    pub fn impl_algebra_traits(&self) -> bool {
+        self.impl_algebra_traits
+    }
+
+    /// Whether the patch struct stores the presence of its values in a bitset.
+    pub fn compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Whether the `get_`, `set_` and `take_` accessors are generated, always for a compact
+    /// patch struct.
+    pub fn accessors(&self) -> bool {
+        self.accessors || self.compact
+    }
+
+    /// The name of the mask struct generated with `#[patch(mask)]`, if any.
+    pub fn mask_struct_name(&self) -> Option<&Ident> {
+        self.mask_struct_name.as_ref()
+    }
+
+    #[cfg_attr(not(feature = "proptest"), allow(dead_code))]
+    pub fn impl_arbitrary_trait(&self) -> bool {
+        self.impl_arbitrary_trait
+    }
+
+    #[cfg_attr(not(feature = "schemars"), allow(dead_code))]
+    pub fn impl_json_schema(&self) -> bool {
+        self.impl_json_schema
+    }
+
+    pub fn impl_deserialize_patch(&self) -> bool {
+        self.impl_deserialize_patch
+    }
+
+    /// The `serde` attributes of the patch struct, read with `#[patch(deserialize_patch)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn serde(&self) -> &SerdeContainer {
+        &self.serde
+    }
+
+    /// The `doc` attributes of the container.
+    pub fn doc_attributes(&self) -> &[Attribute] {
+        &self.doc_attributes
+    }
+
+    /// The `deprecated` attributes of the container.
+    pub fn forwarded_attributes(&self) -> &[Attribute] {
+        &self.forwarded_attributes
+    }
+
+    /// Whether the `doc` and `deprecated` attributes are forwarded to the patch struct and its
+    /// fields.
+    pub fn forward_attributes(&self) -> bool {
+        self.forward_attributes
+    }
+
+    /// Whether the forwarded doc comments are replaced by a generated one.
+    pub fn rewrite_docs(&self) -> bool {
+        self.rewrite_docs
+    }
+
+    /// The doc comment of the container, if any.
+    #[cfg_attr(not(feature = "schemars"), allow(dead_code))]
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
 }
 
 /* -------------------------------------------------------------------------- */
 
 pub struct Field {
+    with: Option<syn::Type>,
+    option_with: bool,
+    elementwise: Option<Elementwise>,
+    default: Option<FieldDefault>,
+    vis: Option<FieldVis>,
     patch_ty: syn::Type,
     path_field_attributes: Vec<Attribute>,
+    serde: SerdeField,
+    doc_attributes: Vec<Attribute>,
+    forwarded_attributes: Vec<Attribute>,
+    doc: Option<String>,
 }
 
 impl Field {
-    pub fn from_ast(cx: &Context, field: &syn::Field) -> Self {
+    pub fn from_ast(cx: &Context, container: &Container, field: &syn::Field) -> Self {
         let mut with = Attr::none(cx, WITH);
+        let mut option_with = Attr::none(cx, OPTION_WITH);
+        let mut elementwise = Attr::none(cx, ELEMENTWISE);
+        let mut elementwise_kind = None;
+        let mut vis = Attr::none(cx, VIS);
+        let mut default = Attr::none(cx, DEFAULT);
         let mut path_field_attributes = VecAttr::none();
 
         for attr in &field.attrs {
@@ -220,6 +585,45 @@ impl Field {
                 if meta.path == WITH {
                     // #[patch(with = FooPatch)]
                     with.set(&meta.path, meta.value()?.parse()?);
+                } else if meta.path == OPTION_WITH {
+                    // #[patch(option_with = FooPatch)]
+                    let patch_ty: syn::Type = meta.value()?.parse()?;
+                    match option_inner_ty(&field.ty) {
+                        Some(ty) => option_with.set(
+                            &meta.path,
+                            parse_quote!(patched::OptionPatch<#ty, #patch_ty>),
+                        ),
+                        None => {
+                            return Err(
+                                meta.error("`option_with` requires a field of type `Option`")
+                            )
+                        }
+                    }
+                } else if meta.path == ELEMENTWISE {
+                    // #[patch(elementwise)]
+                    match elementwise_patch_ty(&field.ty) {
+                        Some((ty, kind)) => {
+                            elementwise.set(&meta.path, ty);
+                            elementwise_kind = Some(kind);
+                        }
+                        None => {
+                            return Err(
+                                meta.error("`elementwise` requires a field of tuple or array type")
+                            )
+                        }
+                    }
+                } else if meta.path == DEFAULT {
+                    if meta.input.peek(syn::Token![=]) {
+                        // #[patch(default = expr)]
+                        default.set(&meta.path, FieldDefault::Expr(meta.value()?.parse()?));
+                    } else {
+                        // #[patch(default)]
+                        default.set(&meta.path, FieldDefault::Default);
+                    }
+                } else if meta.path == VIS {
+                    // #[patch(vis = inherit)]
+                    // #[patch(vis = "pub(crate)")]
+                    vis.set(&meta.path, parse_field_vis(&meta)?);
                 } else {
                     let path = meta.path.to_token_stream().to_string().replace(' ', "");
                     return Err(
@@ -233,15 +637,78 @@ impl Field {
             }
         }
 
+        let option_with_set = option_with.value.is_some();
+        let path_field_attributes = path_field_attributes.get();
+        let serde = if container.impl_deserialize_patch {
+            SerdeField::from_attrs(cx, &path_field_attributes)
+        } else {
+            SerdeField::default()
+        };
+
+        // `option_with` and `elementwise` are shorthands for `with`.
+        let mut with_attrs = [with, option_with, elementwise]
+            .into_iter()
+            .filter(|attr| attr.value.is_some());
+        let with = with_attrs.next().and_then(|attr| attr.value);
+        for attr in with_attrs {
+            cx.error_spanned_by(
+                attr.tokens,
+                "`with`, `option_with` and `elementwise` cannot be used together",
+            );
+        }
+        let patch_ty = with.clone().unwrap_or_else(|| {
+            let ty = &field.ty;
+            parse_quote!(::core::option::Option::<#ty>)
+        });
+
         Field {
-            patch_ty: with.get().unwrap_or_else(|| {
-                let ty = &field.ty;
-                parse_quote!(::core::option::Option::<#ty>)
-            }),
-            path_field_attributes: path_field_attributes.get(),
+            with,
+            option_with: option_with_set,
+            elementwise: elementwise_kind,
+            vis: vis.get(),
+            default: default.get(),
+            patch_ty,
+            path_field_attributes,
+            serde,
+            doc_attributes: get_doc_attributes(&field.attrs),
+            forwarded_attributes: get_forwarded_attributes(&field.attrs),
+            doc: get_doc(&field.attrs),
         }
     }
 
+    /// The type set with `#[patch(with = ...)]`, if any.
+    pub fn with(&self) -> Option<&syn::Type> {
+        self.with.as_ref()
+    }
+
+    /// Whether the patch type was set with `#[patch(option_with = ...)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn option_with(&self) -> bool {
+        self.option_with
+    }
+
+    /// The `serde` attributes of the field of the patch struct, read with
+    /// `#[patch(deserialize_patch)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn serde(&self) -> &SerdeField {
+        &self.serde
+    }
+
+    /// The default value set with `#[patch(default)]` or `#[patch(default = ...)]`, if any.
+    pub(crate) fn default(&self) -> Option<&FieldDefault> {
+        self.default.as_ref()
+    }
+
+    /// The kind of the field with `#[patch(elementwise)]`, if any.
+    pub(crate) fn elementwise(&self) -> Option<Elementwise> {
+        self.elementwise
+    }
+
+    /// The visibility set with `#[patch(vis = ...)]`, if any.
+    pub(crate) fn vis(&self) -> Option<&FieldVis> {
+        self.vis.as_ref()
+    }
+
     pub fn patch_ty(&self) -> &syn::Type {
         &self.patch_ty
     }
@@ -249,6 +716,399 @@ impl Field {
     pub fn path_field_attributes(&self) -> &[Attribute] {
         &self.path_field_attributes
     }
+
+    /// The `doc` attributes of the field.
+    pub fn doc_attributes(&self) -> &[Attribute] {
+        &self.doc_attributes
+    }
+
+    /// The `deprecated` attributes of the field.
+    pub fn forwarded_attributes(&self) -> &[Attribute] {
+        &self.forwarded_attributes
+    }
+
+    /// The doc comment of the field, if any.
+    #[cfg_attr(not(feature = "schemars"), allow(dead_code))]
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+
+/// Returns `T` if `ty` is `Option<T>`.
+fn option_inner_ty(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(ty) = ty else {
+        return None;
+    };
+    if ty.qself.is_some() {
+        return None;
+    }
+
+    let segment = ty.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the element-wise patch type of a tuple or array type: `(Option<A>, Option<B>)` for
+/// `(A, B)` and `[Option<T>; N]` for `[T; N]`.
+fn elementwise_patch_ty(ty: &syn::Type) -> Option<(syn::Type, Elementwise)> {
+    match ty {
+        syn::Type::Tuple(ty) if !ty.elems.is_empty() => {
+            let elems = ty.elems.iter();
+            Some((
+                parse_quote!((#(::core::option::Option<#elems>,)*)),
+                Elementwise::Tuple(ty.elems.len()),
+            ))
+        }
+        syn::Type::Array(ty) => {
+            let elem = &ty.elem;
+            let len = &ty.len;
+            Some((
+                parse_quote!([::core::option::Option<#elem>; #len]),
+                Elementwise::Array,
+            ))
+        }
+        syn::Type::Group(ty) => elementwise_patch_ty(&ty.elem),
+        syn::Type::Paren(ty) => elementwise_patch_ty(&ty.elem),
+        _ => None,
+    }
+}
+
+/// The default value of a field missing from a patch when the struct is built from it.
+pub(crate) enum FieldDefault {
+    /// `#[patch(default)]`: the `Default` value of the type of the field.
+    Default,
+    /// `#[patch(default = expr)]`: the value of the expression.
+    Expr(syn::Expr),
+}
+
+/// The type of a field patched with `#[patch(elementwise)]`.
+#[derive(Clone, Copy)]
+pub(crate) enum Elementwise {
+    /// A tuple with this number of elements.
+    Tuple(usize),
+    /// An array.
+    Array,
+}
+
+/// The visibility of a field of the patch struct.
+pub(crate) enum FieldVis {
+    /// The same visibility as the field of the original struct.
+    Inherit,
+    /// An explicit visibility.
+    Vis(Visibility),
+}
+
+fn parse_field_vis(meta: &ParseNestedMeta) -> syn::Result<FieldVis> {
+    let value = meta.value()?;
+    if value.peek(Ident) {
+        let ident: Ident = value.parse()?;
+        if ident == INHERIT {
+            Ok(FieldVis::Inherit)
+        } else {
+            Err(syn::Error::new_spanned(
+                ident,
+                "expected `inherit` or a string literal",
+            ))
+        }
+    } else {
+        parse_lit_into_vis(&value.parse()?).map(FieldVis::Vis)
+    }
+}
+
+fn parse_lit_into_vis(lit: &syn::LitStr) -> syn::Result<Visibility> {
+    lit.parse().map_err(|_| {
+        syn::Error::new_spanned(
+            lit,
+            format_args!("failed to parse visibility: {:?}", lit.value()),
+        )
+    })
+}
+
+/* -------------------------------------------------------------------------- */
+
+fn get_doc_attributes(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path() == DOC)
+        .cloned()
+        .collect()
+}
+
+/// Collects the attributes, other than `doc`, that are forwarded from the original item to the
+/// patch item.
+fn get_forwarded_attributes(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path() == DEPRECATED)
+        .cloned()
+        .collect()
+}
+
+/// Collects the `#[doc = "..."]` attributes into a single string.
+///
+/// Like rustdoc, the leading space of each line is removed.
+fn get_doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path() == DOC)
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    }),
+                ..
+            }) => Some(lit.value()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let doc = lines
+        .iter()
+        .flat_map(|line| line.lines())
+        .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let doc = doc.trim();
+    (!doc.is_empty()).then(|| doc.to_owned())
 }
 
 /* -------------------------------------------------------------------------- */
+
+/// The `serde` attributes of the patch struct given with `patch_attr`, which change the keys read
+/// by `DeserializePatch`.
+#[derive(Default)]
+pub struct SerdeContainer {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    deny_unknown_fields: bool,
+    default: bool,
+}
+
+impl SerdeContainer {
+    fn from_attrs(cx: &Context, attrs: &[Attribute]) -> Self {
+        let mut rename = Attr::none(cx, RENAME);
+        let mut rename_all = Attr::none(cx, RENAME_ALL);
+        let mut deny_unknown_fields = BoolAttr::none(cx, DENY_UNKNOWN_FIELDS);
+        let mut default = false;
+
+        parse_serde_attrs(cx, attrs, |meta| {
+            if meta.path == RENAME {
+                // #[serde(rename = "Foo")]
+                if let Some(name) = parse_serde_de_value(&meta)? {
+                    rename.set(&meta.path, name.value());
+                }
+            } else if meta.path == RENAME_ALL {
+                // #[serde(rename_all = "camelCase")]
+                if let Some(rule) = parse_serde_de_value(&meta)? {
+                    match RenameRule::from_str(&rule.value()) {
+                        Ok(rule) => rename_all.set(&meta.path, rule),
+                        Err(error) => return Err(syn::Error::new_spanned(rule, error)),
+                    }
+                }
+            } else if meta.path == DENY_UNKNOWN_FIELDS {
+                // #[serde(deny_unknown_fields)]
+                deny_unknown_fields.set_true(&meta.path);
+            } else if meta.path == DEFAULT {
+                // #[serde(default)]
+                // #[serde(default = "...")]
+                default = parse_serde_default(&meta)?;
+            } else if !is_ignored_serde_attr(&meta, &["bound", "crate", "expecting"])? {
+                return Err(unsupported_serde_attr(&meta));
+            }
+            Ok(())
+        });
+
+        SerdeContainer {
+            rename: rename.get(),
+            rename_all: rename_all.get(),
+            deny_unknown_fields: deny_unknown_fields.get(),
+            default,
+        }
+    }
+
+    /// The name set with `#[serde(rename = "...")]`, if any.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
+
+    /// The rule set with `#[serde(rename_all = "...")]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn rename_all(&self) -> RenameRule {
+        self.rename_all.unwrap_or(RenameRule::None)
+    }
+
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn deny_unknown_fields(&self) -> bool {
+        self.deny_unknown_fields
+    }
+
+    /// Whether the fields missing at the end of a sequence are left untouched, with
+    /// `#[serde(default)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn has_default(&self) -> bool {
+        self.default
+    }
+}
+
+/// The `serde` attributes of a field of the patch struct given with `patch_attr`.
+#[derive(Default)]
+pub struct SerdeField {
+    rename: Option<String>,
+    aliases: Vec<String>,
+    skip: bool,
+    default: bool,
+}
+
+impl SerdeField {
+    fn from_attrs(cx: &Context, attrs: &[Attribute]) -> Self {
+        let mut rename = Attr::none(cx, RENAME);
+        let mut aliases = Vec::new();
+        let mut skip = false;
+        let mut default = false;
+
+        parse_serde_attrs(cx, attrs, |meta| {
+            if meta.path == RENAME {
+                // #[serde(rename = "foo")]
+                if let Some(name) = parse_serde_de_value(&meta)? {
+                    rename.set(&meta.path, name.value());
+                }
+            } else if meta.path == ALIAS {
+                // #[serde(alias = "foo")]
+                let alias: syn::LitStr = meta.value()?.parse()?;
+                aliases.push(alias.value());
+            } else if meta.path == SKIP || meta.path == SKIP_DESERIALIZING {
+                // #[serde(skip)]
+                // #[serde(skip_deserializing)]
+                skip = true;
+            } else if meta.path == DEFAULT {
+                // #[serde(default)]
+                // #[serde(default = "...")]
+                default = parse_serde_default(&meta)?;
+            } else if !is_ignored_serde_attr(
+                &meta,
+                &[
+                    "bound",
+                    "serialize_with",
+                    "skip_serializing",
+                    "skip_serializing_if",
+                ],
+            )? {
+                return Err(unsupported_serde_attr(&meta));
+            }
+            Ok(())
+        });
+
+        SerdeField {
+            rename: rename.get(),
+            aliases,
+            skip,
+            default,
+        }
+    }
+
+    /// The name set with `#[serde(rename = "...")]`, if any.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
+
+    /// The names set with `#[serde(alias = "...")]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Whether the field is skipped with `#[serde(skip)]` or `#[serde(skip_deserializing)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn skip(&self) -> bool {
+        self.skip
+    }
+
+    /// Whether the field can be missing at the end of a sequence, with `#[serde(default)]`.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn has_default(&self) -> bool {
+        self.default
+    }
+}
+
+/// Calls `f` with each nested meta of the `serde` attributes of `attrs`.
+fn parse_serde_attrs(
+    cx: &Context,
+    attrs: &[Attribute],
+    mut f: impl FnMut(ParseNestedMeta) -> syn::Result<()>,
+) {
+    for attr in attrs.iter().filter(|attr| attr.path() == SERDE) {
+        if let Err(error) = attr.parse_nested_meta(&mut f) {
+            cx.syn_error(error);
+        }
+    }
+}
+
+/// Parses `name = "value"` or `name(deserialize = "value", serialize = "...")`, and returns the
+/// value used to deserialize, if any.
+fn parse_serde_de_value(meta: &ParseNestedMeta) -> syn::Result<Option<syn::LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut value = None;
+    meta.parse_nested_meta(|meta| {
+        let lit: syn::LitStr = meta.value()?.parse()?;
+        if meta.path == DESERIALIZE {
+            value = Some(lit);
+        } else if meta.path != SERIALIZE {
+            return Err(meta.error("expected `deserialize` or `serialize`"));
+        }
+        Ok(())
+    })?;
+    Ok(value)
+}
+
+/// Parses `default` or `default = "path"`, the value of the missing fields of the patch struct is
+/// the `None` of the patch.
+fn parse_serde_default(meta: &ParseNestedMeta) -> syn::Result<bool> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::LitStr>()?;
+    }
+    Ok(true)
+}
+
+/// Whether the attribute is one of `ignored`, which don't change the deserialization of a patch,
+/// skipping its value if any.
+fn is_ignored_serde_attr(meta: &ParseNestedMeta, ignored: &[&str]) -> syn::Result<bool> {
+    if !ignored.iter().any(|name| meta.path.is_ident(name)) {
+        return Ok(false);
+    }
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+        meta.parse_nested_meta(|meta| {
+            meta.value()?.parse::<syn::Expr>()?;
+            Ok(())
+        })?;
+    }
+    Ok(true)
+}
+
+fn unsupported_serde_attr(meta: &ParseNestedMeta) -> syn::Error {
+    let path = meta.path.to_token_stream().to_string().replace(' ', "");
+    meta.error(format_args!(
+        "`serde({})` is not supported by `deserialize_patch`",
+        path
+    ))
+}
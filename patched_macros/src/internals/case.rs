@@ -0,0 +1,93 @@
+//! The rules of `#[serde(rename_all = "...")]`, which convert the names of the fields to the
+//! keys deserialized by `DeserializePatch`.
+
+use self::RenameRule::*;
+
+/// The case of the keys of the fields.
+#[derive(Copy, Clone, PartialEq)]
+pub enum RenameRule {
+    /// The names of the fields as is.
+    None,
+    /// `lowercase`.
+    LowerCase,
+    /// `UPPERCASE`.
+    UpperCase,
+    /// `PascalCase`.
+    PascalCase,
+    /// `camelCase`.
+    CamelCase,
+    /// `snake_case`.
+    SnakeCase,
+    /// `SCREAMING_SNAKE_CASE`.
+    ScreamingSnakeCase,
+    /// `kebab-case`.
+    KebabCase,
+    /// `SCREAMING-KEBAB-CASE`.
+    ScreamingKebabCase,
+}
+
+static RENAME_RULES: &[(&str, RenameRule)] = &[
+    ("lowercase", LowerCase),
+    ("UPPERCASE", UpperCase),
+    ("PascalCase", PascalCase),
+    ("camelCase", CamelCase),
+    ("snake_case", SnakeCase),
+    ("SCREAMING_SNAKE_CASE", ScreamingSnakeCase),
+    ("kebab-case", KebabCase),
+    ("SCREAMING-KEBAB-CASE", ScreamingKebabCase),
+];
+
+impl RenameRule {
+    /// Parses the value of `rename_all`, returns an error message if it's not a rule.
+    pub fn from_str(rule: &str) -> Result<Self, String> {
+        RENAME_RULES
+            .iter()
+            .find(|(name, _)| *name == rule)
+            .map(|(_, rule)| *rule)
+            .ok_or_else(|| {
+                let expected = RENAME_RULES
+                    .iter()
+                    .map(|(name, _)| format!("{:?}", name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "unknown rename rule `rename_all = {:?}`, expected one of {}",
+                    rule, expected,
+                )
+            })
+    }
+
+    /// Applies the rule to the name of a field, which is in snake case.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn apply_to_field(self, field: &str) -> String {
+        match self {
+            None | LowerCase | SnakeCase => field.to_owned(),
+            UpperCase | ScreamingSnakeCase => field.to_ascii_uppercase(),
+            PascalCase => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            CamelCase => {
+                let pascal = PascalCase.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    Option::None => pascal,
+                }
+            }
+            KebabCase => field.replace('_', "-"),
+            ScreamingKebabCase => ScreamingSnakeCase.apply_to_field(field).replace('_', "-"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use quote::ToTokens;
+use syn::{ext::IdentExt, Member};
+
+use super::{ast::Container, Context};
+
+/// Cross-cutting checks that require the container and all its fields.
+pub fn check(cx: &Context, cont: &Container) {
+    check_method_names(cx, cont);
+    check_field_defaults(cx, cont);
+    check_mask(cx, cont);
+}
+
+/// The setters and the accessors are inherent methods of the patch struct, so their names must be
+/// unique, must not shadow `Default::default` which is called as `FooPatch::default()`, and must
+/// not collide with the other inherent methods.
+fn check_method_names(cx: &Context, cont: &Container) {
+    // The prefixes and the kinds of the methods, and whether they are also generated for the
+    // fields with nested patches.
+    let mut prefixes = vec![(cont.setter_prefix(), "setter", true)];
+    if cont.attrs.accessors() {
+        for prefix in ["get_", "set_", "take_"] {
+            prefixes.push((prefix, "accessor", false));
+        }
+    }
+
+    // The other inherent methods, and the flags generating them.
+    let mut methods = Vec::new();
+    if cont.attrs.impl_minimize_trait() {
+        for method in ["minimize", "minimize_in_place", "is_noop_for"] {
+            methods.push((method, "minimize"));
+        }
+    }
+
+    let mut fields = BTreeMap::new();
+    for field in &cont.fields {
+        for &(prefix, kind, nested) in &prefixes {
+            if !nested && field.attrs.with().is_some()
+                || kind == "setter" && !cont.has_setter(field)
+            {
+                continue;
+            }
+            let Some(name) = field.method_name(prefix) else {
+                continue;
+            };
+
+            let method = name.unraw().to_string();
+            let field_name = field.method_name("").unwrap().unraw().to_string();
+            if method == "default" {
+                cx.error_spanned_by(
+                    &field.member,
+                    "the method `default` generated for this field shadows `Default::default`, \
+                     set a prefix with `#[patch(setters = \"...\")]`",
+                );
+            } else if let Some((_, flag)) = methods.iter().find(|(other, _)| method == *other) {
+                cx.error_spanned_by(
+                    &field.member,
+                    format_args!(
+                        "the {} `{}` of the field `{}` conflicts with the method generated by \
+                         `#[patch({})]`",
+                        kind, method, field_name, flag,
+                    ),
+                );
+            } else if let Some((other_kind, other_field)) =
+                fields.insert(method.clone(), (kind, field_name.clone()))
+            {
+                cx.error_spanned_by(
+                    &field.member,
+                    format_args!(
+                        "the {} `{}` of the field `{}` conflicts with the {} of the field `{}`",
+                        kind, method, field_name, other_kind, other_field,
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// The default values of the fields are only used to build the struct from a patch.
+fn check_field_defaults(cx: &Context, cont: &Container) {
+    if cont.attrs.impl_try_from_trait() {
+        return;
+    }
+
+    for field in cont
+        .fields
+        .iter()
+        .filter(|field| field.attrs.default().is_some())
+    {
+        cx.error_spanned_by(
+            &field.member,
+            "`default` requires `#[patch(try_into)]` on the struct",
+        );
+    }
+}
+
+/// The mask struct isn't generic, it stores the masks of the nested patches, which must not
+/// depend on the type parameters, and has a constant for each field besides `EMPTY` and `ALL`.
+fn check_mask(cx: &Context, cont: &Container) {
+    if cont.attrs.mask_struct_name().is_none() {
+        return;
+    }
+
+    let mut consts = BTreeMap::new();
+    for field in &cont.fields {
+        if field.attrs.with().is_some()
+            && cont.uses_type_params(field.attrs.patch_ty().to_token_stream())
+        {
+            cx.error_spanned_by(
+                &field.member,
+                "`mask` does not support a nested patch whose type uses a type parameter",
+            );
+        }
+
+        let name = field.mask_const_name().to_string();
+        let field_name = match &field.member {
+            Member::Named(ident) => ident.unraw().to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        };
+        if name == "EMPTY" || name == "ALL" {
+            cx.error_spanned_by(
+                &field.member,
+                format_args!(
+                    "the mask constant `{}` of the field `{}` conflicts with `Mask::{}`",
+                    name, field_name, name,
+                ),
+            );
+        } else if let Some(other_field) = consts.insert(name.clone(), field_name.clone()) {
+            cx.error_spanned_by(
+                &field.member,
+                format_args!(
+                    "the mask constant `{}` of the field `{}` conflicts with the one of the \
+                     field `{}`",
+                    name, field_name, other_field,
+                ),
+            );
+        }
+    }
+}
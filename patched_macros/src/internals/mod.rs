@@ -1,7 +1,10 @@
 pub mod ast;
 
 mod attr;
+mod case;
+mod check;
 mod context;
 mod symbol;
 
+pub(crate) use attr::{Elementwise, FieldDefault};
 pub use context::Context;
@@ -4,10 +4,46 @@ use syn::{Ident, Path};
 #[derive(Copy, Clone)]
 pub struct Symbol(&'static str);
 
+pub const ACCESSORS: Symbol = Symbol("accessors");
+pub const ALGEBRA: Symbol = Symbol("algebra");
+pub const ALIAS: Symbol = Symbol("alias");
+pub const ARBITRARY: Symbol = Symbol("arbitrary");
+pub const BY_REF: Symbol = Symbol("by_ref");
+pub const COMPACT: Symbol = Symbol("compact");
+pub const DEFAULT: Symbol = Symbol("default");
+pub const DENY_UNKNOWN_FIELDS: Symbol = Symbol("deny_unknown_fields");
+pub const DEPRECATED: Symbol = Symbol("deprecated");
+pub const DERIVE: Symbol = Symbol("derive");
+pub const DESERIALIZE: Symbol = Symbol("deserialize");
+pub const DESERIALIZE_PATCH: Symbol = Symbol("deserialize_patch");
+pub const DOC: Symbol = Symbol("doc");
+pub const ELEMENTWISE: Symbol = Symbol("elementwise");
+pub const EXPECT: Symbol = Symbol("expect");
+pub const FIELD_VIS: Symbol = Symbol("field_vis");
 pub const FROM: Symbol = Symbol("from");
+pub const INHERIT: Symbol = Symbol("inherit");
+pub const JSON_SCHEMA: Symbol = Symbol("json_schema");
+pub const MASK: Symbol = Symbol("mask");
+pub const MINIMIZE: Symbol = Symbol("minimize");
+pub const NAME: Symbol = Symbol("name");
+pub const NOTIFY: Symbol = Symbol("notify");
+pub const NO_FORWARD_ATTRS: Symbol = Symbol("no_forward_attrs");
+pub const OPTION_WITH: Symbol = Symbol("option_with");
 pub const PATCH: Symbol = Symbol("patch");
 pub const PATCH_ATTR: Symbol = Symbol("patch_attr");
-pub const NAME: Symbol = Symbol("name");
+pub const REMOTE: Symbol = Symbol("remote");
+pub const RENAME: Symbol = Symbol("rename");
+pub const RENAME_ALL: Symbol = Symbol("rename_all");
+pub const REWRITE_DOCS: Symbol = Symbol("rewrite_docs");
+pub const SERDE: Symbol = Symbol("serde");
+pub const SERIALIZE: Symbol = Symbol("serialize");
+pub const SETTERS: Symbol = Symbol("setters");
+pub const SKIP: Symbol = Symbol("skip");
+pub const SKIP_DESERIALIZING: Symbol = Symbol("skip_deserializing");
+pub const TRY_INTO: Symbol = Symbol("try_into");
+pub const TRY_INTO_DEFAULT: Symbol = Symbol("try_into_default");
+pub const TRY_MERGE: Symbol = Symbol("try_merge");
+pub const VIS: Symbol = Symbol("vis");
 pub const WITH: Symbol = Symbol("with");
 
 impl PartialEq<Symbol> for Ident {
@@ -1,5 +1,6 @@
 mod expand;
 mod internals;
+mod literal;
 
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
@@ -9,16 +10,149 @@ use syn::{parse_macro_input, DeriveInput};
 /// This macro will generates a new struct representing the target struct but with all field
 /// being an `Option`.
 ///
+/// The doc comments and the `#[deprecated]` attributes of the struct and its fields are forwarded
+/// to the patch struct. The fields disabled by `#[cfg]` are removed before the macro runs, so they
+/// are not part of the patch struct.
+///
+/// The patch struct implements `Merge` and `Default`, and `MergeFrom` when the types of its fields
+/// implement it. The `Subtract`, `Intersect` and `Restrict` traits require `#[patch(algebra)]`.
+/// With the `wire` feature, it also implements `patched::wire::Wire` and `WireField` when its
+/// fields implement `WireField`, or `Wire` for the values of a `compact` patch.
+///
 /// # Container attributes
 ///
-/// - `#[patch_attr( attribute )]`: put `attribute` on the patch struct.
+/// - `#[patch_attr( attribute )]`: put `attribute` on the patch struct. `Default` must not be
+///   derived, the patch struct implements it.
 /// - `#[patch(name = Foo)]`: set the name of the patch struct.
+/// - `#[patch(remote = other::Foo)]`: generate the patch for a type of another crate, the target
+///   struct is a mirror of the remote type listing the fields to patch. Each field must exist on
+///   the remote type with the same type, this is checked at compile time. The name of the patch
+///   struct defaults to the name of the remote type followed by `Patch`. The `patch!` macro
+///   doesn't support remote types.
+/// - `#[patch(vis = "pub(crate)")]`: set the visibility of the patch struct, defaults to the
+///   visibility of the target struct.
+/// - `#[patch(field_vis = "pub(crate)")]`: set the visibility of the patch struct's fields,
+///   defaults to `pub`. Use `field_vis = inherit` to reuse the visibility of the target struct's
+///   fields. The fields which aren't `pub` get a setter, described below.
 /// - `#[patch(from)]`: impl the `From` trait on the patch struct.
+/// - `#[patch(setters)]` or `#[patch(setters = "prefix_")]`: generate the setters described below.
+/// - `#[patch(accessors)]`: generate the `get_field`, `set_field` and `take_field` methods of the
+///   fields without `with`, `option_with` or `elementwise`, which return, replace and take the
+///   `Option` of the field like the accessors of a `compact` patch struct. It requires a struct
+///   with named fields.
+/// - `#[patch(expect)]`: impl the `Expect` trait for the patch struct, so the fields set on a patch
+///   can be used as expected values of a `Conditional` patch. The fields must implement
+///   `PartialEq`, or `Expect` for nested patches.
+/// - `#[patch(try_merge)]`: impl the `TryMerge` trait on the patch struct, which detects the fields
+///   set to different values by two patches, and keeps both values. The fields must implement
+///   `PartialEq`, `Debug`, `Clone`, `Send` and `Sync` and be `'static`, or nested patches must
+///   implement `TryMerge`.
+/// - `#[patch(by_ref)]`: impl the `PatchRef` trait, which applies a borrowed patch by cloning only
+///   the values it sets. The fields must implement `Clone`, or `PatchRef` for nested patches.
+/// - `#[patch(try_into)]`: impl the `FromPatch` trait and `TryFrom<FooPatch>` for the struct, which
+///   build a value from a patch that sets all the fields and fail with the paths of the missing
+///   fields otherwise.
+/// - `#[patch(try_into_default)]`: with `try_into`, take the missing fields from the `Default` value
+///   of the struct.
+/// - `#[patch(notify)]`: impl the `PatchNotify` trait, which reports the fields whose value
+///   changed when a patch is applied. The fields must implement `PartialEq` and be `'static`, or
+///   implement `PatchNotify` for nested patches.
+/// - `#[patch(minimize)]`: impl the `Minimize` trait on the patch struct, which drops the fields
+///   set to the values they already have on a current value, and the inherent methods `minimize`,
+///   `minimize_in_place` and `is_noop_for` calling it. The fields must implement `PartialEq`, or
+///   nested patches must implement `Minimize`.
+/// - `#[patch(algebra)]`: impl the `Subtract`, `Intersect` and `Restrict` traits on the patch
+///   struct, which remove the fields set by another patch, keep the fields set by another patch
+///   and keep the fields allowed by a list of paths. The fields of the patch struct must
+///   implement them.
+/// - `#[patch(mask)]`: generate a `FooMask` struct, a set of fields like `bitflags`, storing the
+///   fields in a bitset and the masks of the nested patches of the fields with `with` or
+///   `elementwise` in public fields, named after the fields or `_0`, `_1`... for a tuple struct.
+///   It has a constant for each field, `FooMask::FIELD` for `field` or `FooMask::_0`, and
+///   implements the `Mask` trait, with set operations also available as the `|`, `&` and `-`
+///   operators. The patch struct implements `Masked` and `MaskedFrom`, to get the mask of the
+///   fields set by a patch, retain the fields of a mask and build a patch from the fields of a
+///   value. Masks and patches can also be converted from and to dot-separated field paths, like
+///   protobuf `FieldMask`s, naming the fields of nested patches. The types of the nested patches
+///   must not use the type parameters of the struct.
+/// - `#[patch(compact)]`: store the presence of the values of the fields in a bitset instead of
+///   an `Option` for each field, which makes the patch struct smaller. These fields are private
+///   and accessed with the generated `get_field`, `set_field` and `take_field` methods besides the
+///   setters, the fields with `with`, `option_with` or `elementwise` are stored as is. The patch
+///   struct implements `Merge`, `MergeFrom`, `Default`, and `Clone`, `Debug`, `PartialEq` and
+///   `Eq` when the types of its fields implement them, so these traits must not be derived with
+///   `patch_attr`. With `algebra`, it implements `Subtract`, `Intersect` and `Restrict` through
+///   the accessors. It requires a struct with named fields, and can't be used with the other flags
+///   generating trait impls, `json_schema` or the `patch!` macro.
+/// - `#[patch(arbitrary)]`: impl `proptest::arbitrary::Arbitrary` on the patch struct, requires the
+///   `proptest` feature. The fields of the patch struct must implement `Arbitrary`.
+/// - `#[patch(json_schema)]`: impl `schemars::JsonSchema` on the patch struct, requires the
+///   `schemars` feature. Every property is optional, and the types of the fields of the patch
+///   struct must implement `JsonSchema`.
+/// - `#[patch(deserialize_patch)]`: impl `patched::serde::DeserializePatch` on the target struct,
+///   requires the `serde` feature, to deserialize a patch directly into a value like deserializing
+///   the patch struct and applying it. The `serde` attributes set with `patch_attr` rename the keys,
+///   the other attributes changing the format are rejected. The fields must implement
+///   `Deserialize`, the fields with `with` `DeserializePatch` with this patch type, and the patch
+///   types of the fields with `option_with` or `elementwise` `Deserialize`. It requires a struct
+///   with named fields and can't be used with `remote`.
+/// - `#[patch(no_forward_attrs)]`: do not forward the doc comments and the `#[deprecated]`
+///   attributes to the patch struct.
+/// - `#[patch(rewrite_docs)]`: replace the forwarded doc comments by "Patch for `Foo`." and
+///   "Patch for `Foo::field`.".
 ///
 /// # Field attributes
 ///
 /// - `#[patch_attr( attribute )]`: put `attribute` on the patch struct's field.
 /// - `#[patch(with = StructPatch)]`: set the type of the field on the patch struct.
+/// - `#[patch(option_with = StructPatch)]`: for a field of type `Option<Struct>`, set the type of
+///   the field on the patch struct to `patched::OptionPatch<Struct, StructPatch>`, which can clear,
+///   replace or patch the inner value.
+/// - `#[patch(elementwise)]`: for a field of type `(A, B)` or `[T; N]`, set the type of the field
+///   on the patch struct to `(Option<A>, Option<B>)` or `[Option<T>; N]`, so that each element is
+///   patched separately. Nested patches of the elements are set with `with` instead, e.g.
+///   `#[patch(with = (FooPatch, Option<u8>))]`.
+/// - `#[patch(vis = "pub(crate)")]` or `#[patch(vis = inherit)]`: set the visibility of the patch
+///   struct's field, overrides `field_vis`.
+/// - `#[patch(default)]` or `#[patch(default = expr)]`: with `try_into`, use the `Default` value of
+///   the field or the value of `expr` when the field is missing. The nested patch of a `with` field
+///   is applied to this value.
+///
+/// # Smart pointers
+///
+/// The patch also applies to `Box<Foo>`, `RefCell<Foo>` and, if `Foo` implements `Clone`, to
+/// `Rc<Foo>`, `Arc<Foo>` and `Cow<'_, Foo>`, which are cloned on write. So such fields can be
+/// patched with `#[patch(with = FooPatch)]`.
+///
+/// # Setters
+///
+/// With `#[patch(setters)]`, a builder-like setter with the same name as each field and the
+/// visibility of the patch struct is generated. It wraps the value in `Some`, taking the primitive
+/// types as is and the other types as `impl Into<T>`, or takes a closure that configures the
+/// nested patch for fields with `#[patch(with = ...)]`. The setters are named after the fields
+/// with a prefix with `#[patch(setters = "with_")]`, e.g. when a field is named `default` or a
+/// setter collides with another method of the patch struct.
+///
+/// Without `#[patch(setters)]`, the setters are still generated for the fields of a struct with
+/// named fields which aren't `pub` on the patch struct, so the patch can be built where these
+/// fields are private.
+///
+/// ```
+/// # use patched::Patch;
+/// # #[derive(Patch)]
+/// # #[patch(setters)]
+/// # struct Foo {
+/// #     a: u64,
+/// #     #[patch(with = GooPatch)]
+/// #     b: Goo,
+/// # }
+/// # #[derive(Patch)]
+/// # #[patch(setters = "with_")]
+/// # struct Goo {
+/// #     a: String,
+/// # }
+/// let patch = FooPatch::default().a(1).b(|p| p.with_a("Hello"));
+/// ```
 ///
 /// # Example
 ///
@@ -45,3 +179,38 @@ pub fn derive_patch(item: TokenStream) -> TokenStream {
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
+
+/// Creates a patch for a type that derives [`Patch`].
+///
+/// The fields that are not mentioned are left unset. Nested fields, declared with
+/// `#[patch(with = ...)]`, are reached with a dotted path, and can't be set to a value directly.
+/// The values are wrapped in `Some`, and `null` sets a field of type `Option` to `None`. A field
+/// with `#[patch(option_with = ...)]` is set to `Some(value)` or `null`, which replace or clear the
+/// inner value.
+///
+/// # Example
+///
+/// ```
+/// # use patched::{patch, Patch};
+/// #[derive(Patch)]
+/// struct Foo {
+///     a: u64,
+///     #[patch(with = GooPatch)]
+///     b: Goo,
+///     d: Option<bool>,
+/// }
+///
+/// #[derive(Patch)]
+/// struct Goo {
+///     c: &'static str,
+/// }
+///
+/// let patch = patch!(Foo { a: 1, b.c: "x", d: null });
+/// ```
+#[proc_macro]
+pub fn patch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as literal::PatchLiteral);
+    literal::expand_patch_literal(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
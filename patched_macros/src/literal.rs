@@ -0,0 +1,107 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Expr, Ident, Member, Path, Token,
+};
+
+/// The input of the `patch!` macro: `Foo { a: 1, b.c: "x", d: null }`.
+pub struct PatchLiteral {
+    path: Path,
+    fields: Punctuated<FieldValue, Token![,]>,
+}
+
+/// A `b.c: "x"` entry of the `patch!` macro.
+struct FieldValue {
+    members: Punctuated<Member, Token![.]>,
+    value: Value,
+}
+
+/// The value of an entry of the `patch!` macro.
+enum Value {
+    /// `null`, which sets an `Option` to `None`.
+    Null(Ident),
+    /// Any other expression, which is wrapped in `Some`.
+    Expr(Expr),
+}
+
+impl Parse for PatchLiteral {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let content;
+        braced!(content in input);
+        let fields = content.parse_terminated(FieldValue::parse, Token![,])?;
+        Ok(PatchLiteral { path, fields })
+    }
+}
+
+impl Parse for FieldValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let members = Punctuated::parse_separated_nonempty(input)?;
+        input.parse::<Token![:]>()?;
+        let value = match input.parse()? {
+            Expr::Path(expr) if expr.qself.is_none() && expr.path.is_ident("null") => {
+                Value::Null(expr.path.segments[0].ident.clone())
+            }
+            expr => Value::Expr(expr),
+        };
+        Ok(FieldValue { members, value })
+    }
+}
+
+pub fn expand_patch_literal(input: &PatchLiteral) -> Result<TokenStream, syn::Error> {
+    let mut error: Option<syn::Error> = None;
+    for (i, field) in input.fields.iter().enumerate() {
+        let is_duplicate = input
+            .fields
+            .iter()
+            .take(i)
+            .any(|other| other.members.iter().eq(field.members.iter()));
+        if is_duplicate {
+            let members = field.members.iter();
+            let path = quote!(#(#members).*).to_string().replace(' ', "");
+            let duplicate = syn::Error::new_spanned(
+                &field.members,
+                format_args!("field `{}` specified more than once", path),
+            );
+            match &mut error {
+                Some(error) => error.combine(duplicate),
+                None => error = Some(duplicate),
+            }
+        }
+    }
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let path = &input.path;
+
+    let assignments = input.fields.iter().map(|field| {
+        // The members keep the span of the macro input, so an unknown field is reported there.
+        let members = field.members.iter();
+        // The value is converted by the type of the field, so a field with a nested patch is
+        // reported at the value.
+        let value = match &field.value {
+            Value::Expr(value) => quote_spanned! {value.span()=>
+                patched::__private::LiteralValue::value(#value)
+            },
+            Value::Null(null) => quote_spanned! {null.span()=>
+                patched::__private::LiteralNull::null()
+            },
+        };
+        quote! {
+            patch.#(#members).* = #value;
+        }
+    });
+
+    Ok(quote! {
+        {
+            let mut patch: <#path as patched::Patchable>::Patch = ::core::default::Default::default();
+            #(#assignments)*
+            patch
+        }
+    })
+}
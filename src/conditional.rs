@@ -0,0 +1,142 @@
+use std::{error::Error, fmt};
+
+use crate::{FieldPath, Patch, TryPatch};
+
+/// A check of the current value of `self` against expected values.
+///
+/// It's implemented by the derive macro with `#[patch(expect)]` for the patch struct, so that the
+/// fields set on the patch are the expected values and the other fields are not checked.
+pub trait Expect<E> {
+    /// Pushes to `mismatches` the path of each field that doesn't match its expected value, `path`
+    /// being the path of `self`.
+    fn mismatches_at(&self, expected: &E, path: &mut FieldPath, mismatches: &mut Vec<FieldPath>);
+
+    /// Returns the paths of the fields that don't match their expected values.
+    #[inline]
+    fn mismatches(&self, expected: &E) -> Vec<FieldPath> {
+        let mut mismatches = Vec::new();
+        self.mismatches_at(expected, &mut FieldPath::new(), &mut mismatches);
+        mismatches
+    }
+
+    /// Returns `true` if every field matches its expected value.
+    #[inline]
+    fn matches(&self, expected: &E) -> bool {
+        self.mismatches(expected).is_empty()
+    }
+}
+
+impl<T> Expect<Option<T>> for T
+where
+    T: PartialEq,
+{
+    /// Checks that `self` is equal to the expected value if it is `Some`.
+    #[inline]
+    fn mismatches_at(
+        &self,
+        expected: &Option<T>,
+        path: &mut FieldPath,
+        mismatches: &mut Vec<FieldPath>,
+    ) {
+        if expected.as_ref().is_some_and(|expected| self != expected) {
+            mismatches.push(path.clone());
+        }
+    }
+}
+
+/// A patch applied only if the current value matches expected values, for compare-and-set
+/// updates.
+///
+/// It's applied with [`TryPatch`], which returns a [`Conflict`] listing the mismatched fields
+/// instead of applying the patch.
+///
+/// ```
+/// # use patched::{Conditional, Patch, TryPatch};
+/// #[derive(Patch, Debug, PartialEq)]
+/// #[patch(expect, setters)]
+/// struct Account {
+///     version: u32,
+///     balance: u64,
+/// }
+///
+/// let mut account = Account { version: 1, balance: 100 };
+///
+/// let patch = Conditional::new(
+///     AccountPatch::default().version(1),
+///     AccountPatch::default().version(2).balance(50),
+/// );
+/// assert!(account.try_patch(patch).is_ok());
+/// assert_eq!(account, Account { version: 2, balance: 50 });
+///
+/// let patch = Conditional::new(
+///     AccountPatch::default().version(1),
+///     AccountPatch::default().version(2).balance(0),
+/// );
+/// let conflict = account.try_patch(patch).unwrap_err();
+/// assert_eq!(conflict.to_string(), "unexpected value of the field `version`");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Conditional<E, P = E> {
+    /// The expected values.
+    pub expected: E,
+    /// The patch applied if the current value matches `expected`.
+    pub patch: P,
+}
+
+impl<E, P> Conditional<E, P> {
+    /// Creates a patch applied only if the current value matches `expected`.
+    #[inline]
+    pub fn new(expected: E, patch: P) -> Self {
+        Self { expected, patch }
+    }
+}
+
+impl<T, E, P> TryPatch<Conditional<E, P>> for T
+where
+    T: Expect<E> + Patch<P>,
+{
+    type Error = Conflict;
+
+    /// Applies the patch if every field matches its expected value, otherwise returns the
+    /// mismatched fields and leaves `self` untouched.
+    #[inline]
+    fn try_patch(&mut self, patch: Conditional<E, P>) -> Result<(), Self::Error> {
+        let fields = self.mismatches(&patch.expected);
+        if !fields.is_empty() {
+            return Err(Conflict { fields });
+        }
+
+        self.patch(patch.patch);
+        Ok(())
+    }
+}
+
+/// The error returned when a [`Conditional`] patch doesn't apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    fields: Vec<FieldPath>,
+}
+
+impl Conflict {
+    /// Returns the paths of the fields that don't match their expected values.
+    #[inline]
+    pub fn fields(&self) -> &[FieldPath] {
+        &self.fields
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.fields.len() == 1 { "" } else { "s" };
+        write!(f, "unexpected value of the field{plural} ")?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{field}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Conflict {}
@@ -0,0 +1,432 @@
+//! Element-wise patches of tuples and arrays.
+//!
+//! A tuple `(A, B)` is patched element by element with a tuple `(PA, PB)` where `A: Patch<PA>` and
+//! `B: Patch<PB>`, e.g. `(Option<A>, Option<B>)`, and an array `[T; N]` with an array `[P; N]`
+//! where `T: Patch<P>`, e.g. `[Option<T>; N]`. The other traits are implemented element by element
+//! too, the index of an element being its segment in a `FieldPath`.
+
+use crate::{
+    Change, Expect, FieldPath, FromPatch, Intersect, Mask, Masked, MaskedFrom, Merge,
+    MergeConflict, MergeFrom, Minimize, Patch, PatchNotify, PatchRef, Restrict, Subtract, TryMerge,
+};
+
+/// Expands to an element-wise operation on tuples, `(tuple 0 1 ...)`, or arrays, `(array N)`.
+///
+/// An operation is written `{ |index| (a = mode x, ...) { body } }`: the body is evaluated for each
+/// element with `a` bound to the element of `x`, moved, borrowed or mutably borrowed for the `val`,
+/// `ref` and `mut` modes, and `index` to the segment of the element.
+macro_rules! elementwise {
+    (@type (tuple $($index:tt)+) [$($ty:ty),+]) => {
+        ($($ty,)+)
+    };
+    (@type (array $n:ident) [$ty:ty]) => {
+        [$ty; $n]
+    };
+
+    (@for_each (tuple $($index:tt)+) $op:tt) => {
+        $(elementwise!(@at $index $op);)+
+    };
+    (@for_each (array $n:ident) {
+        $(|$i:ident|)? ($($b:ident = $mode:ident $x:expr),*) $body:block
+    }) => {
+        $(let mut $b = elementwise!(@iter $mode $x);)*
+        for _index in 0..$n {
+            $(let $b = $b.next().unwrap();)*
+            $(let $i = _index.to_string();)?
+            $body
+        }
+    };
+
+    // Collects the results in a tuple or an array.
+    (@map (tuple $($index:tt)+) $op:tt) => {
+        ($(elementwise!(@at $index $op),)+)
+    };
+    (@map (array $n:ident) {
+        $(|$i:ident|)? ($($b:ident = $mode:ident $x:expr),*) $body:block
+    }) => {{
+        $(let mut $b = elementwise!(@iter $mode $x);)*
+        ::core::array::from_fn(|_index| {
+            $(let $b = $b.next().unwrap();)*
+            $(let $i = _index.to_string();)?
+            $body
+        })
+    }};
+
+    // The tuple or the array of the constant `$c` of each element type.
+    (@const (tuple $($index:tt)+) [$($ty:ident),+] $c:ident) => {
+        ($($ty::$c,)+)
+    };
+    (@const (array $n:ident) [$ty:ident] $c:ident) => {
+        [$ty::$c; $n]
+    };
+
+    // Returns whether the results are all `true`.
+    (@all (tuple $($index:tt)+) $op:tt) => {
+        ($(elementwise!(@at $index $op))&&+)
+    };
+    (@all (array $n:ident) { ($($b:ident = $mode:ident $x:expr),*) $body:block }) => {{
+        $(let mut $b = elementwise!(@iter $mode $x);)*
+        (0..$n).all(|_| {
+            $(let $b = $b.next().unwrap();)*
+            $body
+        })
+    }};
+
+    // Returns the result for the element whose segment is `$segment`, if any.
+    (@find (tuple $($index:tt)+) $segment:expr, $b:ident = mut $x:expr => $body:expr) => {
+        match $segment {
+            $(stringify!($index) => ::core::option::Option::Some({
+                let $b = &mut $x.$index;
+                $body
+            }),)+
+            _ => ::core::option::Option::None,
+        }
+    };
+    (@find (array $n:ident) $segment:expr, $b:ident = mut $x:expr => $body:expr) => {
+        $segment
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| $x.get_mut(index))
+            .map(|$b| $body)
+    };
+
+    // Turns a tuple or an array of `Option`s into an `Option` of a tuple or an array.
+    (@transpose (tuple $($index:tt)+) $values:ident) => {
+        ::core::option::Option::Some(($($values.$index?,)+))
+    };
+    (@transpose (array $n:ident) $values:ident) => {
+        if $values.iter().any(::core::option::Option::is_none) {
+            ::core::option::Option::None
+        } else {
+            ::core::option::Option::Some($values.map(::core::option::Option::unwrap))
+        }
+    };
+
+    (@at $index:tt { $(|$i:ident|)? ($($b:ident = $mode:ident $x:expr),*) $body:block }) => {{
+        $(let $b = elementwise!(@get $mode $x, $index);)*
+        $(let $i = stringify!($index);)?
+        $body
+    }};
+
+    (@get val $x:expr, $index:tt) => { $x.$index };
+    (@get ref $x:expr, $index:tt) => { &$x.$index };
+    (@get mut $x:expr, $index:tt) => { &mut $x.$index };
+
+    (@iter val $x:expr) => { ::core::iter::IntoIterator::into_iter($x) };
+    (@iter ref $x:expr) => { $x.iter() };
+    (@iter mut $x:expr) => { $x.iter_mut() };
+}
+
+/// Implements the traits for a tuple or an array, given the pairs of the element types of the value
+/// and the patch, and the additional generic parameters.
+macro_rules! impl_elementwise {
+    ($kind:tt [$($v:ident $p:ident),+] $($generics:tt)*) => {
+        impl_elementwise! {
+            @impl $kind [$($v $p),+] [$($generics)*]
+            elementwise!(@type $kind [$($v),+]),
+            elementwise!(@type $kind [$($p),+]),
+            elementwise!(@type $kind [$($p::Output),+]),
+            elementwise!(@type $kind [$($p::Mask),+])
+        }
+    };
+
+    (
+        @impl $kind:tt [$($v:ident $p:ident),+] [$($generics:tt)*]
+        $value:ty, $patch:ty, $output:ty, $mask:ty
+    ) => {
+        impl<$($v, $p,)+ $($generics)*> Patch<$patch> for $value
+        where
+            $($v: Patch<$p>,)+
+        {
+            #[inline]
+            fn patch(&mut self, patch: $patch) {
+                elementwise!(@for_each $kind {
+                    (value = mut self, patch = val patch) { value.patch(patch); }
+                });
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> PatchRef<$patch> for $value
+        where
+            $($v: PatchRef<$p>,)+
+        {
+            #[inline]
+            fn patch_ref(&mut self, patch: &$patch) {
+                elementwise!(@for_each $kind {
+                    (value = mut self, patch = ref patch) { value.patch_ref(patch); }
+                });
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> PatchNotify<$patch> for $value
+        where
+            $($v: PatchNotify<$p>,)+
+        {
+            fn patch_notify_at(
+                &mut self,
+                patch: $patch,
+                path: &mut FieldPath,
+                observer: &mut dyn FnMut(Change<'_>),
+            ) {
+                elementwise!(@for_each $kind {
+                    |index| (value = mut self, patch = val patch) {
+                        path.push(index);
+                        value.patch_notify_at(patch, path, observer);
+                        path.pop();
+                    }
+                });
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> FromPatch<$patch> for $value
+        where
+            $($v: FromPatch<$p>,)+
+        {
+            fn from_patch_at(
+                patch: $patch,
+                path: &mut FieldPath,
+                missing: &mut Vec<FieldPath>,
+            ) -> Option<Self> {
+                let values = elementwise!(@map $kind {
+                    |index| (patch = val patch) {
+                        path.push(index);
+                        let value = FromPatch::from_patch_at(patch, path, missing);
+                        path.pop();
+                        value
+                    }
+                });
+                elementwise!(@transpose $kind values)
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> Expect<$patch> for $value
+        where
+            $($v: Expect<$p>,)+
+        {
+            fn mismatches_at(
+                &self,
+                expected: &$patch,
+                path: &mut FieldPath,
+                mismatches: &mut Vec<FieldPath>,
+            ) {
+                elementwise!(@for_each $kind {
+                    |index| (value = ref self, expected = ref expected) {
+                        path.push(index);
+                        value.mismatches_at(expected, path, mismatches);
+                        path.pop();
+                    }
+                });
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> Minimize<$value> for $patch
+        where
+            $($p: Minimize<$v>,)+
+        {
+            #[inline]
+            fn minimize_in_place(&mut self, current: &$value) {
+                elementwise!(@for_each $kind {
+                    (patch = mut self, current = ref current) { patch.minimize_in_place(current); }
+                });
+            }
+
+            #[inline]
+            fn is_noop_for(&self, current: &$value) -> bool {
+                elementwise!(@all $kind {
+                    (patch = ref self, current = ref current) { patch.is_noop_for(current) }
+                })
+            }
+        }
+
+        impl<$($v, $p,)+ $($generics)*> MaskedFrom<$value> for $patch
+        where
+            $($p: MaskedFrom<$v>,)+
+        {
+            #[inline]
+            fn from_value_masked(value: &$value, mask: Self::Mask) -> Self {
+                elementwise!(@map $kind {
+                    (value = ref value, mask = val mask) {
+                        MaskedFrom::from_value_masked(value, mask)
+                    }
+                })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Merge for $patch
+        where
+            $($p: Merge,)+
+        {
+            type Output = $output;
+
+            #[inline]
+            fn merge(self, rhs: Self) -> Self::Output {
+                elementwise!(@map $kind { (lhs = val self, rhs = val rhs) { lhs.merge(rhs) } })
+            }
+
+            #[inline]
+            fn is_empty(&self) -> bool {
+                elementwise!(@all $kind { (patch = ref self) { patch.is_empty() } })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> MergeFrom for $patch
+        where
+            $($p: MergeFrom,)+
+        {
+            #[inline]
+            fn merge_from(&mut self, rhs: Self) {
+                elementwise!(@for_each $kind {
+                    (lhs = mut self, rhs = val rhs) { lhs.merge_from(rhs); }
+                });
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> TryMerge for $patch
+        where
+            $($p: TryMerge,)+
+        {
+            fn merge_conflicts_at(
+                &self,
+                rhs: &Self,
+                path: &mut FieldPath,
+                conflicts: &mut Vec<MergeConflict>,
+            ) {
+                elementwise!(@for_each $kind {
+                    |index| (lhs = ref self, rhs = ref rhs) {
+                        path.push(index);
+                        lhs.merge_conflicts_at(rhs, path, conflicts);
+                        path.pop();
+                    }
+                });
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Subtract for $patch
+        where
+            $($p: Subtract,)+
+        {
+            #[inline]
+            fn subtract(self, rhs: &Self) -> Self {
+                elementwise!(@map $kind { (lhs = val self, rhs = ref rhs) { lhs.subtract(rhs) } })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Intersect for $patch
+        where
+            $($p: Intersect,)+
+        {
+            #[inline]
+            fn intersect(self, rhs: &Self) -> Self {
+                elementwise!(@map $kind { (lhs = val self, rhs = ref rhs) { lhs.intersect(rhs) } })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Restrict for $patch
+        where
+            $($p: Restrict,)+
+        {
+            fn restrict_at(self, allowed: &[FieldPath], path: &mut FieldPath) -> Self {
+                elementwise!(@map $kind {
+                    |index| (patch = val self) {
+                        path.push(index);
+                        let patch = patch.restrict_at(allowed, path);
+                        path.pop();
+                        patch
+                    }
+                })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Masked for $patch
+        where
+            $($p: Masked,)+
+        {
+            type Mask = $mask;
+
+            #[inline]
+            fn mask(&self) -> Self::Mask {
+                elementwise!(@map $kind { (patch = ref self) { patch.mask() } })
+            }
+
+            #[inline]
+            fn retain(self, mask: Self::Mask) -> Self {
+                elementwise!(@map $kind {
+                    (patch = val self, mask = val mask) { patch.retain(mask) }
+                })
+            }
+        }
+
+        impl<$($p,)+ $($generics)*> Mask for $patch
+        where
+            $($p: Mask,)+
+        {
+            const EMPTY: Self = elementwise!(@const $kind [$($p),+] EMPTY);
+            const ALL: Self = elementwise!(@const $kind [$($p),+] ALL);
+
+            #[inline]
+            fn union(self, other: Self) -> Self {
+                elementwise!(@map $kind { (lhs = val self, rhs = val other) { lhs.union(rhs) } })
+            }
+
+            #[inline]
+            fn intersection(self, other: Self) -> Self {
+                elementwise!(@map $kind {
+                    (lhs = val self, rhs = val other) { lhs.intersection(rhs) }
+                })
+            }
+
+            #[inline]
+            fn difference(self, other: Self) -> Self {
+                elementwise!(@map $kind {
+                    (lhs = val self, rhs = val other) { lhs.difference(rhs) }
+                })
+            }
+
+            fn insert_path(&mut self, segments: &[&str]) -> bool {
+                match segments.split_first() {
+                    None => {
+                        *self = Self::all();
+                        true
+                    }
+                    Some((first, rest)) => {
+                        elementwise!(@find $kind *first, mask = mut self => mask.insert_path(rest))
+                            .unwrap_or(false)
+                    }
+                }
+            }
+
+            fn paths_at(&self, path: &mut FieldPath, paths: &mut Vec<FieldPath>) {
+                elementwise!(@for_each $kind {
+                    |index| (mask = ref self) {
+                        path.push(index);
+                        mask.paths_at(path, paths);
+                        path.pop();
+                    }
+                });
+            }
+        }
+    };
+}
+
+impl_elementwise!((array N) [T P] const N: usize);
+
+impl_elementwise!((tuple 0) [A PA]);
+impl_elementwise!((tuple 0 1) [A PA, B PB]);
+impl_elementwise!((tuple 0 1 2) [A PA, B PB, C PC]);
+impl_elementwise!((tuple 0 1 2 3) [A PA, B PB, C PC, D PD]);
+impl_elementwise!((tuple 0 1 2 3 4) [A PA, B PB, C PC, D PD, E PE]);
+impl_elementwise!((tuple 0 1 2 3 4 5) [A PA, B PB, C PC, D PD, E PE, F PF]);
+impl_elementwise!((tuple 0 1 2 3 4 5 6) [A PA, B PB, C PC, D PD, E PE, F PF, G PG]);
+impl_elementwise!((tuple 0 1 2 3 4 5 6 7) [A PA, B PB, C PC, D PD, E PE, F PF, G PG, H PH]);
+impl_elementwise!((tuple 0 1 2 3 4 5 6 7 8) [A PA, B PB, C PC, D PD, E PE, F PF, G PG, H PH, I PI]);
+impl_elementwise!(
+    (tuple 0 1 2 3 4 5 6 7 8 9) [A PA, B PB, C PC, D PD, E PE, F PF, G PG, H PH, I PI, J PJ]
+);
+impl_elementwise!(
+    (tuple 0 1 2 3 4 5 6 7 8 9 10)
+    [A PA, B PB, C PC, D PD, E PE, F PF, G PG, H PH, I PI, J PJ, K PK]
+);
+impl_elementwise!(
+    (tuple 0 1 2 3 4 5 6 7 8 9 10 11)
+    [A PA, B PB, C PC, D PD, E PE, F PF, G PG, H PH, I PI, J PJ, K PK, L PL]
+);
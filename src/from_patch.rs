@@ -0,0 +1,90 @@
+use std::{error::Error, fmt};
+
+use crate::FieldPath;
+
+/// A value built from a patch that sets all its fields.
+///
+/// It's implemented by the derive macro with `#[patch(try_into)]`, along with
+/// `TryFrom<FooPatch> for Foo`, recursing into nested patches. The missing fields can be filled
+/// with `#[patch(default)]` or `#[patch(default = expr)]`.
+///
+/// ```
+/// # use patched::{FromPatch, Patch};
+/// #[derive(Patch, Debug, PartialEq)]
+/// #[patch(try_into, setters)]
+/// struct User {
+///     name: String,
+///     email: String,
+///     #[patch(default)]
+///     admin: bool,
+/// }
+///
+/// let patch = UserPatch::default().name("Alice").email("alice@example.com");
+/// assert_eq!(
+///     User::try_from(patch),
+///     Ok(User { name: "Alice".into(), email: "alice@example.com".into(), admin: false }),
+/// );
+///
+/// let error = User::try_from_patch(UserPatch::default().name("Bob")).unwrap_err();
+/// assert_eq!(error.to_string(), "missing field `email`");
+/// ```
+pub trait FromPatch<P>: Sized {
+    /// Builds a value from `patch`, or pushes to `missing` the path of each field that is not set,
+    /// `path` being the path of the value.
+    fn from_patch_at(patch: P, path: &mut FieldPath, missing: &mut Vec<FieldPath>) -> Option<Self>;
+
+    /// Builds a value from `patch`, or returns the fields that are not set.
+    #[inline]
+    fn try_from_patch(patch: P) -> Result<Self, MissingFields> {
+        let mut missing = Vec::new();
+        match Self::from_patch_at(patch, &mut FieldPath::new(), &mut missing) {
+            Some(value) if missing.is_empty() => Ok(value),
+            _ => Err(MissingFields { fields: missing }),
+        }
+    }
+}
+
+impl<T> FromPatch<Option<T>> for T {
+    /// Returns the value of `patch` if it is `Some`.
+    #[inline]
+    fn from_patch_at(
+        patch: Option<T>,
+        path: &mut FieldPath,
+        missing: &mut Vec<FieldPath>,
+    ) -> Option<Self> {
+        if patch.is_none() {
+            missing.push(path.clone());
+        }
+        patch
+    }
+}
+
+/// The error returned when a value is built from a patch that doesn't set all its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingFields {
+    fields: Vec<FieldPath>,
+}
+
+impl MissingFields {
+    /// Returns the paths of the missing fields.
+    #[inline]
+    pub fn fields(&self) -> &[FieldPath] {
+        &self.fields
+    }
+}
+
+impl fmt::Display for MissingFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.fields.len() == 1 { "" } else { "s" };
+        write!(f, "missing field{plural} ")?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{field}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MissingFields {}
@@ -0,0 +1,54 @@
+//! Support for [`schemars`], used by the code generated by the derive macro.
+
+use serde_json::{Map, Value};
+
+pub use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+/// Builds the schema of a patch struct with named fields.
+///
+/// None of the properties are required, since each field of a patch may be omitted.
+pub fn object_schema<const N: usize>(
+    description: Option<&str>,
+    properties: [(&str, Schema, Option<&str>); N],
+) -> Schema {
+    let properties = properties
+        .into_iter()
+        .map(|(name, mut schema, description)| {
+            if let Some(description) = description {
+                schema.insert("description".into(), description.into());
+            }
+            (name.to_owned(), schema.to_value())
+        })
+        .collect::<Map<_, _>>();
+
+    let mut schema = Map::new();
+    schema.insert("type".into(), "object".into());
+    schema.insert("properties".into(), Value::Object(properties));
+    with_description(schema, description)
+}
+
+/// Builds the schema of a patch struct with unnamed fields.
+pub fn tuple_schema<const N: usize>(description: Option<&str>, items: [Schema; N]) -> Schema {
+    let items = items.into_iter().map(Schema::to_value).collect::<Vec<_>>();
+
+    let mut schema = Map::new();
+    schema.insert("type".into(), "array".into());
+    schema.insert("minItems".into(), N.into());
+    schema.insert("maxItems".into(), N.into());
+    schema.insert("prefixItems".into(), Value::Array(items));
+    with_description(schema, description)
+}
+
+/// Builds the schema of a patch struct without fields.
+pub fn unit_schema(description: Option<&str>) -> Schema {
+    let mut schema = Map::new();
+    schema.insert("type".into(), "null".into());
+    with_description(schema, description)
+}
+
+fn with_description(mut schema: Map<String, Value>, description: Option<&str>) -> Schema {
+    if let Some(description) = description {
+        schema.insert("description".into(), description.into());
+    }
+    Schema::from(schema)
+}
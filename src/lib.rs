@@ -1,5 +1,53 @@
 #[cfg(feature = "macros")]
-pub use ::patched_macros::Patch;
+pub use ::patched_macros::{patch, Patch};
+
+mod conditional;
+mod elementwise;
+mod from_patch;
+#[cfg(feature = "schemars")]
+mod json_schema;
+mod literal;
+mod log;
+mod mask;
+mod minimize;
+mod notify;
+mod option;
+mod path;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod try_merge;
+#[cfg(feature = "wire")]
+pub mod wire;
+
+pub use conditional::{Conditional, Conflict, Expect};
+pub use from_patch::{FromPatch, MissingFields};
+pub use log::PatchLog;
+pub use mask::{Mask, Masked, MaskedFrom, UnknownPath};
+pub use minimize::Minimize;
+pub use notify::{Change, PatchNotify};
+pub use option::OptionPatch;
+pub use path::FieldPath;
+pub use try_merge::{MergeConflict, MergeError, TryMerge};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::literal::{LiteralNull, LiteralValue};
+
+    #[cfg(feature = "proptest")]
+    pub use proptest;
+
+    #[cfg(feature = "schemars")]
+    pub mod schemars {
+        pub use crate::json_schema::*;
+    }
+
+    #[cfg(feature = "serde")]
+    pub mod serde {
+        pub use ::serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess};
+    }
+}
 
 /// Modify partially or totally the value of `self` from a patch value.
 pub trait Patch<P> {
@@ -27,6 +75,57 @@ impl<T> Patch<Option<T>> for T {
     }
 }
 
+/// Modify partially or totally the value of `self` from a borrowed patch value.
+///
+/// Only the values set on the patch are cloned, so one patch can be applied to many values
+/// without cloning the whole patch for each of them. It's implemented by the derive macro with
+/// `#[patch(by_ref)]`.
+pub trait PatchRef<P> {
+    /// Modify partially or totally the value of `self` from a borrowed patch value.
+    fn patch_ref(&mut self, patch: &P);
+
+    /// Consumes `self` and returns a patched version.
+    #[inline]
+    fn with_patch_ref(mut self, patch: &P) -> Self
+    where
+        Self: Sized,
+    {
+        self.patch_ref(patch);
+        self
+    }
+}
+
+impl<T> PatchRef<Option<T>> for T
+where
+    T: Clone,
+{
+    /// Sets the value of `self` to a clone of the value of `patch` if it is `Some`.
+    #[inline]
+    fn patch_ref(&mut self, patch: &Option<T>) {
+        if let Some(value) = patch {
+            self.clone_from(value);
+        }
+    }
+}
+
+/// Modify partially or totally the value of `self` from a patch value that may not apply.
+pub trait TryPatch<P> {
+    /// The error returned when the patch doesn't apply.
+    type Error;
+
+    /// Modify partially or totally the value of `self` from a patch value, or returns an error
+    /// and leaves `self` untouched if the patch doesn't apply.
+    fn try_patch(&mut self, patch: P) -> Result<(), Self::Error>;
+}
+
+/// A type with a patch type generated by the derive macro.
+///
+/// This is used by the `patch!` macro to find the patch type of a type.
+pub trait Patchable {
+    /// The patch type generated for this type.
+    type Patch;
+}
+
 /// An operator that merges two patch values.
 ///
 /// This operation must be coherent with [`Patch`] so that the two following codes are equivalent.
@@ -50,6 +149,15 @@ pub trait Merge<Rhs = Self> {
 
     /// Merges `self` and `rhs`.
     fn merge(self, rhs: Rhs) -> Self::Output;
+
+    /// Returns `true` if the patch leaves every value unchanged, like the `Default` patch.
+    ///
+    /// It lets a patch of a copy-on-write value skip the copy. The default implementation returns
+    /// `false`, which is always correct.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        false
+    }
 }
 
 impl<T> Merge for Option<T> {
@@ -59,13 +167,183 @@ impl<T> Merge for Option<T> {
     fn merge(self, rhs: Self) -> Self::Output {
         rhs.or(self)
     }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.is_none()
+    }
+}
+
+/// An in-place [`Merge`], which avoids moving and rebuilding large patches.
+///
+/// This operation must be coherent with [`Merge`] so that the two following codes are equivalent,
+/// and so it's also coherent with [`Patch`].
+///
+/// ```no_run
+/// # use patched::{Patch, Merge, MergeFrom};
+/// # #[derive(Patch)] struct Foo;
+/// # let mut patch_1: FooPatch = loop {};
+/// # let patch_2: FooPatch = loop {};
+/// // 1
+/// patch_1 = patch_1.merge(patch_2);
+///
+/// // 2
+/// patch_1.merge_from(patch_2);
+/// ```
+///
+/// It's implemented by the derive macro for the patch struct when the types of its fields
+/// implement it.
+pub trait MergeFrom<Rhs = Self>: Merge<Rhs, Output = Self> {
+    /// Merges `rhs` into `self`.
+    fn merge_from(&mut self, rhs: Rhs);
+
+    /// Merges all the patches, from the first to the last, into the default patch.
+    #[inline]
+    fn merge_all(patches: impl IntoIterator<Item = Rhs>) -> Self
+    where
+        Self: Default,
+    {
+        let mut merged = Self::default();
+        for patch in patches {
+            merged.merge_from(patch);
+        }
+        merged
+    }
+}
+
+impl<T> MergeFrom for Option<T> {
+    #[inline]
+    fn merge_from(&mut self, rhs: Self) {
+        if rhs.is_some() {
+            *self = rhs;
+        }
+    }
+}
+
+/// An operator that removes from a patch the fields also set by another patch.
+///
+/// It's implemented by the derive macro for the patch struct with `#[patch(algebra)]`, the fields
+/// of nested patches are subtracted individually.
+///
+/// ```
+/// # use patched::{Patch, Subtract};
+/// #[derive(Patch)]
+/// #[patch(algebra, setters)]
+/// #[patch_attr(derive(Debug, PartialEq))]
+/// struct Foo {
+///     a: u8,
+///     b: u8,
+/// }
+///
+/// let patch = FooPatch::default().a(1).b(2);
+/// assert_eq!(patch.subtract(&FooPatch::default().a(3)), FooPatch::default().b(2));
+/// ```
+pub trait Subtract {
+    /// Returns `self` without the fields set by `rhs`.
+    #[must_use]
+    fn subtract(self, rhs: &Self) -> Self;
+}
+
+impl<T> Subtract for Option<T> {
+    /// Returns `None` if `rhs` is `Some`, `self` otherwise.
+    #[inline]
+    fn subtract(self, rhs: &Self) -> Self {
+        if rhs.is_some() {
+            None
+        } else {
+            self
+        }
+    }
+}
+
+/// An operator that keeps only the fields of a patch also set by another patch.
+///
+/// The values are taken from `self`, so comparing `a.intersect(&b)` with `b.intersect(&a)` shows
+/// the fields set by both patches to different values. It's implemented by the derive macro for
+/// the patch struct with `#[patch(algebra)]`, the fields of nested patches are intersected
+/// individually.
+///
+/// ```
+/// # use patched::{Intersect, Patch};
+/// #[derive(Patch)]
+/// #[patch(algebra, setters)]
+/// #[patch_attr(derive(Debug, PartialEq))]
+/// struct Foo {
+///     a: u8,
+///     b: u8,
+/// }
+///
+/// let patch = FooPatch::default().a(1).b(2);
+/// assert_eq!(patch.intersect(&FooPatch::default().a(3)), FooPatch::default().a(1));
+/// ```
+pub trait Intersect {
+    /// Returns `self` with only the fields also set by `rhs`.
+    #[must_use]
+    fn intersect(self, rhs: &Self) -> Self;
+}
+
+impl<T> Intersect for Option<T> {
+    /// Returns `self` if `rhs` is `Some`, `None` otherwise.
+    #[inline]
+    fn intersect(self, rhs: &Self) -> Self {
+        if rhs.is_some() {
+            self
+        } else {
+            None
+        }
+    }
+}
+
+/// An operator that keeps only the fields of a patch allowed by a list of paths.
+///
+/// A field is allowed if one of the paths is its path or the path of one of its parents, e.g.
+/// `server` allows `server.port` and all the other fields of `server`. It's implemented by the
+/// derive macro for the patch struct with `#[patch(algebra)]`.
+///
+/// ```
+/// # use patched::{FieldPath, Patch, Restrict};
+/// #[derive(Patch)]
+/// #[patch(algebra, setters)]
+/// #[patch_attr(derive(Debug, PartialEq))]
+/// struct Foo {
+///     a: u8,
+///     b: u8,
+/// }
+///
+/// let allowed = [FieldPath::from_iter(["b"])];
+/// let patch = FooPatch::default().a(1).b(2);
+/// assert_eq!(patch.restrict(&allowed), FooPatch::default().b(2));
+/// ```
+pub trait Restrict: Sized {
+    /// Returns `self` with only the fields allowed by `allowed`, `path` being the path of `self`.
+    #[must_use]
+    fn restrict_at(self, allowed: &[FieldPath], path: &mut FieldPath) -> Self;
+
+    /// Returns `self` with only the fields allowed by `allowed`.
+    #[inline]
+    #[must_use]
+    fn restrict(self, allowed: &[FieldPath]) -> Self {
+        self.restrict_at(allowed, &mut FieldPath::new())
+    }
+}
+
+impl<T> Restrict for Option<T> {
+    /// Returns `self` if `path` is allowed, `None` otherwise.
+    #[inline]
+    fn restrict_at(self, allowed: &[FieldPath], path: &mut FieldPath) -> Self {
+        if path.is_allowed_by(allowed) {
+            self
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::fmt::Debug;
 
-    use crate::{Merge, Patch};
+    use crate::{FieldPath, Intersect, Merge, MergeFrom, Patch, Restrict, Subtract};
 
     fn test_merge<T, P>(value: T, patches: impl IntoIterator<Item = P>) -> T
     where
@@ -102,4 +380,66 @@ mod tests {
 
         assert_eq!(test_merge(99, [None, None, None]), 99);
     }
+
+    #[test]
+    fn merge_from() {
+        let mut patch = Some(1);
+        patch.merge_from(None);
+        assert_eq!(patch, Some(1));
+        patch.merge_from(Some(2));
+        assert_eq!(patch, Some(2));
+
+        assert_eq!(
+            <(Option<u8>, [Option<u8>; 2])>::merge_all([
+                (Some(1), [None, Some(2)]),
+                (None, [Some(3), None]),
+                (Some(4), [None, None]),
+            ]),
+            (Some(4), [Some(3), Some(2)])
+        );
+    }
+
+    #[test]
+    fn tuple_merge() {
+        assert_eq!(
+            test_merge((1, 'a'), [(Some(2), None), (None, Some('b')), (None, None)]),
+            (2, 'b')
+        );
+
+        assert_eq!(
+            test_merge(
+                (1, (2, 3)),
+                [(None, (Some(4), None)), (Some(5), (None, Some(6)))]
+            ),
+            (5, (4, 6))
+        );
+    }
+
+    #[test]
+    fn array_merge() {
+        assert_eq!(
+            test_merge([1, 2, 3], [[Some(4), None, None], [None, None, Some(5)]]),
+            [4, 2, 5]
+        );
+
+        assert_eq!(test_merge([1, 2], [[None, None]]), [1, 2]);
+    }
+
+    #[test]
+    fn patch_algebra() {
+        let patch = (Some(1), [Some(2), None], Some(3));
+        let other = (None, [Some(4), Some(5)], Some(6));
+
+        assert_eq!(patch.subtract(&other), (Some(1), [None, None], None));
+        assert_eq!(patch.intersect(&other), (None, [Some(2), None], Some(3)));
+        assert_eq!(other.intersect(&patch), (None, [Some(4), None], Some(6)));
+
+        let allowed = [
+            FieldPath::from_iter(["1", "0"]),
+            FieldPath::from_iter(["2"]),
+        ];
+        assert_eq!(patch.restrict(&allowed), (None, [Some(2), None], Some(3)));
+        assert_eq!(patch.restrict(&[FieldPath::new()]), patch);
+        assert_eq!(patch.restrict(&[]), (None, [None, None], None));
+    }
 }
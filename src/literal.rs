@@ -0,0 +1,56 @@
+//! Support for the `patch!` macro, used by the code generated by the macro.
+
+use crate::OptionPatch;
+
+/// A field of a patch that can be set to a value with the `patch!` macro.
+#[diagnostic::on_unimplemented(
+    message = "a value can't be set on a field of type `{Self}` with `patch!`",
+    label = "this field has a nested patch",
+    note = "set the fields of the nested patch with a dotted path, like `field.inner: value`"
+)]
+pub trait LiteralValue<T> {
+    /// The field set to `value`.
+    fn value(value: T) -> Self;
+}
+
+impl<T> LiteralValue<T> for Option<T> {
+    #[inline]
+    fn value(value: T) -> Self {
+        Some(value)
+    }
+}
+
+/// `Some(value)` replaces the inner value and `None` clears it.
+impl<T, P> LiteralValue<Option<T>> for OptionPatch<T, P> {
+    #[inline]
+    fn value(value: Option<T>) -> Self {
+        value.into()
+    }
+}
+
+/// A field of a patch that can be set to `null` with the `patch!` macro.
+#[diagnostic::on_unimplemented(
+    message = "`null` can't be set on a field of type `{Self}` with `patch!`",
+    label = "this field is not an `Option`",
+    note = "`null` sets a field of type `Option` to `None`"
+)]
+pub trait LiteralNull {
+    /// The field set to `null`.
+    fn null() -> Self;
+}
+
+/// `null` sets the field to `None`.
+impl<T> LiteralNull for Option<Option<T>> {
+    #[inline]
+    fn null() -> Self {
+        Some(None)
+    }
+}
+
+/// `null` clears the inner value.
+impl<T, P> LiteralNull for OptionPatch<T, P> {
+    #[inline]
+    fn null() -> Self {
+        OptionPatch::Clear
+    }
+}
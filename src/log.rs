@@ -0,0 +1,210 @@
+use std::ops::Range;
+
+use crate::{Merge, Patch};
+
+/// A log of patches applied to a snapshot, for event sourcing.
+///
+/// The patches are numbered by their position in the log: the patch at position `i` turns the
+/// state at position `i` into the state at position `i + 1`. The snapshot is the state at the
+/// [`start`](Self::start) position, which is `0` unless the log was started from a later
+/// snapshot or [checkpointed](Self::checkpoint_at).
+///
+/// Runs of patches can be [compacted](Self::compact) into a single merged patch, after which the
+/// states in the middle of the run are no longer available. Since [`Merge`] is coherent with
+/// [`Patch`], compaction doesn't change the other states.
+///
+/// ```
+/// # use patched::{Patch, PatchLog};
+/// #[derive(Patch, Clone, Debug, PartialEq)]
+/// #[patch(setters)]
+/// #[patch_attr(derive(Clone))]
+/// struct Counter {
+///     name: String,
+///     value: u32,
+/// }
+///
+/// let mut log = PatchLog::new(Counter { name: "a".into(), value: 0 });
+/// log.append(CounterPatch::default().value(1));
+/// log.append(CounterPatch::default().name("b"));
+/// log.append(CounterPatch::default().value(2));
+///
+/// assert_eq!(log.materialize(), Counter { name: "b".into(), value: 2 });
+/// assert_eq!(log.snapshot_at(1), Some(Counter { name: "a".into(), value: 1 }));
+///
+/// log.compact(0..3);
+/// assert_eq!(log.entries().count(), 1);
+/// assert_eq!(log.snapshot_at(1), None);
+/// assert_eq!(log.materialize(), Counter { name: "b".into(), value: 2 });
+/// ```
+#[derive(Debug, Clone)]
+pub struct PatchLog<T, P> {
+    snapshot: T,
+    start: usize,
+    entries: Vec<Entry<P>>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<P> {
+    patch: P,
+    /// The position after the patch: the entry covers the patches from the end of the previous
+    /// entry up to this position.
+    end: usize,
+}
+
+impl<T, P> PatchLog<T, P> {
+    /// Creates an empty log from the initial state.
+    #[inline]
+    pub fn new(snapshot: T) -> Self {
+        Self::from_snapshot(snapshot, 0)
+    }
+
+    /// Creates an empty log from the state at `position`.
+    #[inline]
+    pub fn from_snapshot(snapshot: T, position: usize) -> Self {
+        Self {
+            snapshot,
+            start: position,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the snapshot, which is the state at the [`start`](Self::start) position.
+    #[inline]
+    pub fn snapshot(&self) -> &T {
+        &self.snapshot
+    }
+
+    /// Returns the position of the snapshot.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the position of the current state, after the last patch.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.entries.last().map_or(self.start, |entry| entry.end)
+    }
+
+    /// Returns `true` if the log has no patches.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Appends a patch at the end of the log.
+    #[inline]
+    pub fn append(&mut self, patch: P) {
+        let end = self.end() + 1;
+        self.entries.push(Entry { patch, end });
+    }
+
+    /// Returns the patches of the log with the range of positions they cover, which is more than
+    /// one position for compacted patches.
+    pub fn entries(&self) -> impl Iterator<Item = (Range<usize>, &P)> + '_ {
+        self.entries
+            .iter()
+            .zip(self.entry_starts())
+            .map(|(entry, start)| (start..entry.end, &entry.patch))
+    }
+
+    /// Returns the start position of each entry.
+    fn entry_starts(&self) -> impl Iterator<Item = usize> + '_ {
+        let ends = self.entries.iter().map(|entry| entry.end);
+        std::iter::once(self.start)
+            .chain(ends)
+            .take(self.entries.len())
+    }
+
+    /// Returns the number of entries before `position`, if `position` is the start or the end of
+    /// an entry.
+    fn entries_before(&self, position: usize) -> Option<usize> {
+        if position == self.start {
+            return Some(0);
+        }
+
+        self.entries
+            .binary_search_by_key(&position, |entry| entry.end)
+            .ok()
+            .map(|index| index + 1)
+    }
+}
+
+impl<T, P> PatchLog<T, P>
+where
+    T: Patch<P> + Clone,
+    P: Clone,
+{
+    /// Returns the current state by applying every patch to the snapshot.
+    pub fn materialize(&self) -> T {
+        self.replay(self.entries.len())
+    }
+
+    /// Returns the state at `position`.
+    ///
+    /// Returns `None` if `position` is before the snapshot, after the end of the log, or inside a
+    /// run of compacted patches.
+    pub fn snapshot_at(&self, position: usize) -> Option<T> {
+        let count = self.entries_before(position)?;
+        Some(self.replay(count))
+    }
+
+    /// Replaces the snapshot with the state at `position` and removes the patches before it.
+    ///
+    /// Returns `false` and leaves the log untouched if there is no state at `position`, see
+    /// [`snapshot_at`](Self::snapshot_at).
+    pub fn checkpoint_at(&mut self, position: usize) -> bool {
+        let Some(count) = self.entries_before(position) else {
+            return false;
+        };
+
+        self.snapshot = self.replay(count);
+        self.start = position;
+        self.entries.drain(..count);
+        true
+    }
+
+    fn replay(&self, count: usize) -> T {
+        let mut state = self.snapshot.clone();
+        for entry in &self.entries[..count] {
+            state.patch(entry.patch.clone());
+        }
+        state
+    }
+}
+
+impl<T, P> PatchLog<T, P>
+where
+    P: Merge<Output = P>,
+{
+    /// Merges the patches covering `positions` into a single patch.
+    ///
+    /// Only the entries entirely inside `positions` are merged, so the range is shrunk to the
+    /// boundaries of the entries.
+    pub fn compact(&mut self, positions: Range<usize>) {
+        let first = self
+            .entry_starts()
+            .position(|start| start >= positions.start);
+        let last = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.end <= positions.end);
+
+        let (Some(first), Some(last)) = (first, last) else {
+            return;
+        };
+        if first >= last {
+            return;
+        }
+
+        let entry = {
+            let mut entries = self.entries.drain(first..=last);
+            let first = entries.next().unwrap();
+            entries.fold(first, |merged, entry| Entry {
+                patch: merged.patch.merge(entry.patch),
+                end: entry.end,
+            })
+        };
+        self.entries.insert(first, entry);
+    }
+}
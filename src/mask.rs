@@ -0,0 +1,291 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug},
+    hash::Hash,
+};
+
+use crate::FieldPath;
+
+/// A set of fields, without their values.
+///
+/// It's implemented for `bool`, a single field which is either in the set or not, for tuples and
+/// arrays of masks and for the mask structs generated by the derive macro with `#[patch(mask)]`.
+/// Like `bitflags`, a mask struct stores its fields in a bitset, has a constant for each field,
+/// named after the field in uppercase, and supports the `|`, `&` and `-` operators. The masks of
+/// the fields with nested patches are public fields instead.
+///
+/// A mask can be converted from and to a list of dot-separated paths, like a protobuf
+/// `FieldMask`. A path to a nested mask includes all its fields.
+///
+/// ```
+/// # use patched::{Mask, Patch};
+/// #[derive(Patch)]
+/// #[patch(mask)]
+/// struct Config {
+///     name: String,
+///     #[patch(with = ServerPatch)]
+///     server: Server,
+/// }
+///
+/// #[derive(Patch)]
+/// #[patch(mask)]
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let mut mask = ConfigMask::NAME;
+/// mask.server = ServerMask::PORT;
+/// assert!(mask.contains(ConfigMask::NAME));
+/// assert_eq!(ConfigMask::from_paths(["name", "server.port"]).unwrap(), mask);
+///
+/// let paths: Vec<String> = mask.to_paths().iter().map(ToString::to_string).collect();
+/// assert_eq!(paths, ["name", "server.port"]);
+///
+/// let mask = ConfigMask::from_paths(["server"]).unwrap();
+/// assert_eq!(mask, ConfigMask::SERVER);
+/// assert_eq!(mask.server, ServerMask::ALL);
+///
+/// let error = ConfigMask::from_paths(["server.tls"]).unwrap_err();
+/// assert_eq!(error.to_string(), "unknown field path `server.tls`");
+/// ```
+pub trait Mask: Copy + Eq + Hash + Debug {
+    /// The set without fields.
+    const EMPTY: Self;
+
+    /// The set of all the fields.
+    const ALL: Self;
+
+    /// Returns [`Mask::EMPTY`].
+    #[inline]
+    fn empty() -> Self {
+        Self::EMPTY
+    }
+
+    /// Returns [`Mask::ALL`].
+    #[inline]
+    fn all() -> Self {
+        Self::ALL
+    }
+
+    /// The fields in `self` or in `other`.
+    #[must_use]
+    fn union(self, other: Self) -> Self;
+
+    /// The fields in both `self` and `other`.
+    #[must_use]
+    fn intersection(self, other: Self) -> Self;
+
+    /// The fields in `self` but not in `other`.
+    #[must_use]
+    fn difference(self, other: Self) -> Self;
+
+    /// Adds the field at the path made of `segments`, relative to `self`, and all its nested
+    /// fields. Returns `false` if there's no such field.
+    fn insert_path(&mut self, segments: &[&str]) -> bool;
+
+    /// Pushes the paths of the fields of the set to `paths`, `path` being the path of `self`.
+    fn paths_at(&self, path: &mut FieldPath, paths: &mut Vec<FieldPath>);
+
+    /// Builds the set of the fields at the dot-separated `paths`.
+    fn from_paths<I>(paths: I) -> Result<Self, UnknownPath>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut mask = Self::empty();
+        for path in paths {
+            let path = path.as_ref();
+            let segments: Vec<&str> = path.split('.').collect();
+            if !mask.insert_path(&segments) {
+                return Err(UnknownPath {
+                    path: path.to_owned(),
+                });
+            }
+        }
+        Ok(mask)
+    }
+
+    /// Returns the paths of the fields of the set. The paths of nested masks name their fields.
+    fn to_paths(&self) -> Vec<FieldPath> {
+        let mut paths = Vec::new();
+        self.paths_at(&mut FieldPath::new(), &mut paths);
+        paths
+    }
+
+    /// Returns `true` if the set has no fields.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        *self == Self::empty()
+    }
+
+    /// Returns `true` if the set has all the fields.
+    #[inline]
+    fn is_all(&self) -> bool {
+        *self == Self::all()
+    }
+
+    /// Returns `true` if all the fields of `other` are in `self`.
+    #[inline]
+    fn contains(&self, other: Self) -> bool {
+        other.difference(*self).is_empty()
+    }
+
+    /// Adds the fields of `other` to `self`.
+    #[inline]
+    fn insert(&mut self, other: Self) {
+        *self = self.union(other);
+    }
+
+    /// Removes the fields of `other` from `self`.
+    #[inline]
+    fn remove(&mut self, other: Self) {
+        *self = self.difference(other);
+    }
+}
+
+impl Mask for bool {
+    const EMPTY: Self = false;
+    const ALL: Self = true;
+
+    #[inline]
+    fn union(self, other: Self) -> Self {
+        self | other
+    }
+
+    #[inline]
+    fn intersection(self, other: Self) -> Self {
+        self & other
+    }
+
+    #[inline]
+    fn difference(self, other: Self) -> Self {
+        self & !other
+    }
+
+    /// Only the empty path is valid.
+    #[inline]
+    fn insert_path(&mut self, segments: &[&str]) -> bool {
+        if segments.is_empty() {
+            *self = true;
+        }
+        segments.is_empty()
+    }
+
+    #[inline]
+    fn paths_at(&self, path: &mut FieldPath, paths: &mut Vec<FieldPath>) {
+        if *self {
+            paths.push(path.clone());
+        }
+    }
+}
+
+/// The error returned by [`Mask::from_paths`] for a path that doesn't match a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPath {
+    path: String,
+}
+
+impl UnknownPath {
+    /// Returns the unknown path.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for UnknownPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown field path `{}`", self.path)
+    }
+}
+
+impl Error for UnknownPath {}
+
+/// A patch whose set fields can be listed as a [`Mask`].
+///
+/// It's implemented by the derive macro for the patch struct with `#[patch(mask)]`, along with a
+/// mask struct with a bit for each field, or the mask of the nested patch for the fields with
+/// `with` or `elementwise`.
+///
+/// ```
+/// # use patched::{Mask, Masked, MaskedFrom, Patch};
+/// #[derive(Patch)]
+/// #[patch(mask, setters)]
+/// #[patch_attr(derive(Debug, PartialEq))]
+/// struct Window {
+///     title: String,
+///     width: u32,
+/// }
+///
+/// let patch = WindowPatch::default().title("a").width(200);
+/// let mask = WindowMask::TITLE;
+///
+/// assert_eq!(patch.mask(), WindowMask::all());
+/// assert_eq!(patch.retain(mask), WindowPatch::default().title("a"));
+///
+/// let window = Window { title: "b".into(), width: 100 };
+/// assert_eq!(
+///     WindowPatch::from_value_masked(&window, mask),
+///     WindowPatch::default().title("b")
+/// );
+/// ```
+pub trait Masked {
+    /// The mask of the fields of the patch.
+    type Mask: Mask;
+
+    /// Returns the fields set by `self`.
+    fn mask(&self) -> Self::Mask;
+
+    /// Returns `self` with only the fields set in `mask`.
+    #[must_use]
+    fn retain(self, mask: Self::Mask) -> Self;
+
+    /// Returns the paths of the fields set by `self`, see [`Mask::to_paths`].
+    #[inline]
+    fn paths(&self) -> Vec<FieldPath> {
+        self.mask().to_paths()
+    }
+}
+
+/// A [`Masked`] patch that can be built from the fields of a value.
+pub trait MaskedFrom<T>: Masked {
+    /// Builds a patch setting the fields of `mask` to their value on `value`.
+    fn from_value_masked(value: &T, mask: Self::Mask) -> Self;
+
+    /// Builds a patch setting the fields at the dot-separated `paths` to their value on `value`,
+    /// like an update request with a protobuf `FieldMask`, see [`Mask::from_paths`].
+    #[inline]
+    fn from_value_paths<I>(value: &T, paths: I) -> Result<Self, UnknownPath>
+    where
+        Self: Sized,
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Ok(Self::from_value_masked(value, Mask::from_paths(paths)?))
+    }
+}
+
+impl<T> Masked for Option<T> {
+    type Mask = bool;
+
+    #[inline]
+    fn mask(&self) -> Self::Mask {
+        self.is_some()
+    }
+
+    #[inline]
+    fn retain(self, mask: Self::Mask) -> Self {
+        self.filter(|_| mask)
+    }
+}
+
+impl<T> MaskedFrom<T> for Option<T>
+where
+    T: Clone,
+{
+    #[inline]
+    fn from_value_masked(value: &T, mask: Self::Mask) -> Self {
+        mask.then(|| value.clone())
+    }
+}
@@ -0,0 +1,62 @@
+/// A patch that can drop the parts that wouldn't change a value.
+///
+/// A minimized patch has the same effect on `current` as the original one, but it doesn't set the
+/// fields to the values they already have. The values are compared with `PartialEq`. It's
+/// implemented by the derive macro for the patch struct with `#[patch(minimize)]`, minimizing the
+/// fields of nested patches individually.
+///
+/// ```
+/// # use patched::{Minimize, Patch};
+/// #[derive(Patch)]
+/// #[patch(minimize, setters)]
+/// #[patch_attr(derive(Debug, PartialEq))]
+/// struct Window {
+///     title: String,
+///     width: u32,
+/// }
+///
+/// let window = Window { title: "a".into(), width: 100 };
+///
+/// let patch = WindowPatch::default().title("a").width(200);
+/// assert_eq!(patch.minimize(&window), WindowPatch::default().width(200));
+///
+/// assert!(WindowPatch::default().title("a").is_noop_for(&window));
+/// ```
+pub trait Minimize<T> {
+    /// Drops the parts of `self` that wouldn't change `current`.
+    fn minimize_in_place(&mut self, current: &T);
+
+    /// Returns whether applying `self` to `current` would leave it unchanged.
+    fn is_noop_for(&self, current: &T) -> bool;
+
+    /// Consumes `self` and returns a version without the parts that wouldn't change `current`.
+    #[inline]
+    fn minimize(mut self, current: &T) -> Self
+    where
+        Self: Sized,
+    {
+        self.minimize_in_place(current);
+        self
+    }
+}
+
+impl<T> Minimize<T> for Option<T>
+where
+    T: PartialEq,
+{
+    /// Sets `self` to `None` if its value is equal to `current`.
+    #[inline]
+    fn minimize_in_place(&mut self, current: &T) {
+        if self.is_noop_for(current) {
+            *self = None;
+        }
+    }
+
+    #[inline]
+    fn is_noop_for(&self, current: &T) -> bool {
+        match self {
+            Some(value) => value == current,
+            None => true,
+        }
+    }
+}
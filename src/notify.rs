@@ -0,0 +1,123 @@
+use std::{any::Any, fmt};
+
+use crate::{FieldPath, Patch};
+
+/// A [`Patch`] that reports the fields whose value actually changed.
+///
+/// Setting a field to its current value is not a change, the values are compared with
+/// `PartialEq`. It's implemented by the derive macro with `#[patch(notify)]`, reporting the
+/// changes of the fields of nested patches individually.
+///
+/// The old and new values are passed to the observer as `&dyn Any` in a [`Change`], so the types
+/// of the fields must be `'static`: a field can't borrow data, e.g. `&'a str`.
+///
+/// ```
+/// # use patched::{FieldPath, Patch, PatchNotify};
+/// #[derive(Patch)]
+/// #[patch(notify, setters)]
+/// struct Window {
+///     title: String,
+///     width: u32,
+/// }
+///
+/// let mut window = Window { title: "a".into(), width: 100 };
+///
+/// window.patch_notify(WindowPatch::default().title("a").width(200), |change| {
+///     assert_eq!(change.path().to_string(), "width");
+///     assert_eq!(change.old_value::<u32>(), Some(&100));
+///     assert_eq!(change.new_value::<u32>(), Some(&200));
+/// });
+///
+/// let changes = window.patch_changes(WindowPatch::default().title("b").width(200));
+/// assert_eq!(changes, [FieldPath::from_iter(["title"])]);
+/// ```
+pub trait PatchNotify<P>: Patch<P> {
+    /// Applies `patch` and calls `observer` for each field that changed, `path` being the path of
+    /// `self`.
+    fn patch_notify_at(
+        &mut self,
+        patch: P,
+        path: &mut FieldPath,
+        observer: &mut dyn FnMut(Change<'_>),
+    );
+
+    /// Applies `patch` and calls `observer` for each field that changed.
+    #[inline]
+    fn patch_notify(&mut self, patch: P, mut observer: impl FnMut(Change<'_>)) {
+        self.patch_notify_at(patch, &mut FieldPath::new(), &mut observer);
+    }
+
+    /// Applies `patch` and returns the paths of the fields that changed.
+    #[inline]
+    fn patch_changes(&mut self, patch: P) -> Vec<FieldPath> {
+        let mut changes = Vec::new();
+        self.patch_notify(patch, |change| changes.push(change.path().clone()));
+        changes
+    }
+}
+
+impl<T> PatchNotify<Option<T>> for T
+where
+    T: PartialEq + 'static,
+{
+    /// Sets the value of `self` if `patch` is `Some`, and reports a change if it's a different
+    /// value.
+    #[inline]
+    fn patch_notify_at(
+        &mut self,
+        patch: Option<T>,
+        path: &mut FieldPath,
+        observer: &mut dyn FnMut(Change<'_>),
+    ) {
+        if let Some(value) = patch {
+            if *self != value {
+                let old = std::mem::replace(self, value);
+                observer(Change::new(path, &old, self));
+            }
+        }
+    }
+}
+
+/// A change of the value of a field, reported by [`PatchNotify`].
+///
+/// The values are downcast to the type of the field, which must be `'static`.
+#[derive(Clone, Copy)]
+pub struct Change<'a> {
+    path: &'a FieldPath,
+    old: &'a dyn Any,
+    new: &'a dyn Any,
+}
+
+impl<'a> Change<'a> {
+    /// Creates a change of the field at `path` from `old` to `new`.
+    #[inline]
+    pub fn new(path: &'a FieldPath, old: &'a dyn Any, new: &'a dyn Any) -> Self {
+        Self { path, old, new }
+    }
+
+    /// Returns the path of the field.
+    #[inline]
+    pub fn path(&self) -> &'a FieldPath {
+        self.path
+    }
+
+    /// Returns the value of the field before the patch, if it's of type `T`.
+    #[inline]
+    pub fn old_value<T: Any>(&self) -> Option<&'a T> {
+        self.old.downcast_ref()
+    }
+
+    /// Returns the value of the field after the patch, if it's of type `T`.
+    #[inline]
+    pub fn new_value<T: Any>(&self) -> Option<&'a T> {
+        self.new.downcast_ref()
+    }
+}
+
+impl fmt::Debug for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Change")
+            .field("path", self.path)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,464 @@
+use std::fmt::Debug;
+
+use crate::{
+    Change, Expect, FieldPath, FromPatch, Intersect, Masked, MaskedFrom, Merge, MergeConflict,
+    MergeFrom, Minimize, Patch, PatchNotify, PatchRef, Restrict, Subtract, TryMerge,
+};
+
+/// A patch for an `Option` whose inner value can itself be patched.
+///
+/// Unlike `Option<Option<T>>`, which can only replace the whole value, this patch can update the
+/// inner value in place.
+///
+/// With the `serde` feature, it implements `Serialize` and `Deserialize` as an externally tagged
+/// enum, e.g. `"Clear"` or `{ "Set": value }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(crate = "::serde")
+)]
+pub enum OptionPatch<T, P> {
+    /// Leaves the value untouched.
+    #[default]
+    Keep,
+    /// Sets the value to `None`.
+    Clear,
+    /// Sets the value to `Some`.
+    Set(T),
+    /// Patches the inner value if there is one.
+    Patch {
+        /// The patch applied to the inner value.
+        patch: P,
+        /// If the value is `None` and this is `Some`, the value is initialized from `T::default()`
+        /// and patched with it. Otherwise a `None` value is left absent.
+        or_insert: Option<P>,
+    },
+}
+
+impl<T, P> OptionPatch<T, P> {
+    /// Patches the inner value if there is one, a `None` value is left absent.
+    #[inline]
+    pub fn update(patch: P) -> Self {
+        Self::Patch {
+            patch,
+            or_insert: None,
+        }
+    }
+
+    /// Patches the inner value, a `None` value is first initialized from `T::default()`.
+    #[inline]
+    pub fn upsert(patch: P) -> Self
+    where
+        P: Clone,
+    {
+        Self::Patch {
+            patch: patch.clone(),
+            or_insert: Some(patch),
+        }
+    }
+}
+
+impl<T, P> From<Option<T>> for OptionPatch<T, P> {
+    /// Converts `Some(value)` into [`OptionPatch::Set`] and `None` into [`OptionPatch::Clear`].
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Self::Set(value),
+            None => Self::Clear,
+        }
+    }
+}
+
+impl<T, P> Patch<OptionPatch<T, P>> for Option<T>
+where
+    T: Patch<P> + Default,
+{
+    #[inline]
+    fn patch(&mut self, patch: OptionPatch<T, P>) {
+        match patch {
+            OptionPatch::Keep => {}
+            OptionPatch::Clear => *self = None,
+            OptionPatch::Set(value) => *self = Some(value),
+            OptionPatch::Patch { patch, or_insert } => match self {
+                Some(value) => value.patch(patch),
+                None => {
+                    if let Some(init) = or_insert {
+                        *self = Some(T::default().with_patch(init));
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl<T, P> PatchRef<OptionPatch<T, P>> for Option<T>
+where
+    T: PatchRef<P> + Clone + Default,
+{
+    #[inline]
+    fn patch_ref(&mut self, patch: &OptionPatch<T, P>) {
+        match patch {
+            OptionPatch::Keep => {}
+            OptionPatch::Clear => *self = None,
+            OptionPatch::Set(value) => match self {
+                Some(current) => current.clone_from(value),
+                None => *self = Some(value.clone()),
+            },
+            OptionPatch::Patch { patch, or_insert } => match self {
+                Some(value) => value.patch_ref(patch),
+                None => {
+                    if let Some(init) = or_insert {
+                        *self = Some(T::default().with_patch_ref(init));
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl<T, P> PatchNotify<OptionPatch<T, P>> for Option<T>
+where
+    T: PatchNotify<P> + Default + PartialEq + 'static,
+{
+    /// Reports the changes of the inner value when it's patched, otherwise reports a change of the
+    /// whole value.
+    fn patch_notify_at(
+        &mut self,
+        patch: OptionPatch<T, P>,
+        path: &mut FieldPath,
+        observer: &mut dyn FnMut(Change<'_>),
+    ) {
+        let value = match (patch, &mut *self) {
+            (OptionPatch::Keep, _) => return,
+            (OptionPatch::Clear, _) => None,
+            (OptionPatch::Set(value), _) => Some(value),
+            (OptionPatch::Patch { patch, .. }, Some(value)) => {
+                return value.patch_notify_at(patch, path, observer);
+            }
+            (OptionPatch::Patch { or_insert, .. }, None) => match or_insert {
+                Some(init) => Some(T::default().with_patch(init)),
+                None => return,
+            },
+        };
+
+        if *self != value {
+            let old = std::mem::replace(self, value);
+            observer(Change::new(path, &old, self));
+        }
+    }
+}
+
+impl<T, P> FromPatch<OptionPatch<T, P>> for Option<T>
+where
+    T: FromPatch<P>,
+{
+    /// `Clear` builds `None`, `Set` builds `Some` with its value and `Patch` builds `Some` with the
+    /// value built from the inner patch. Nothing is built from `Keep`.
+    fn from_patch_at(
+        patch: OptionPatch<T, P>,
+        path: &mut FieldPath,
+        missing: &mut Vec<FieldPath>,
+    ) -> Option<Self> {
+        match patch {
+            OptionPatch::Keep => {
+                missing.push(path.clone());
+                None
+            }
+            OptionPatch::Clear => Some(None),
+            OptionPatch::Set(value) => Some(Some(value)),
+            OptionPatch::Patch { patch, .. } => T::from_patch_at(patch, path, missing).map(Some),
+        }
+    }
+}
+
+impl<T, P> Merge for OptionPatch<T, P>
+where
+    T: Patch<P> + Default,
+    P: Merge<Output = P> + Clone,
+{
+    type Output = Self;
+
+    fn merge(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            (lhs, OptionPatch::Keep) => lhs,
+            (_, rhs @ (OptionPatch::Clear | OptionPatch::Set(_))) => rhs,
+            (OptionPatch::Keep, rhs @ OptionPatch::Patch { .. }) => rhs,
+            (OptionPatch::Clear, OptionPatch::Patch { or_insert, .. }) => match or_insert {
+                Some(init) => OptionPatch::Set(T::default().with_patch(init)),
+                None => OptionPatch::Clear,
+            },
+            (OptionPatch::Set(value), OptionPatch::Patch { patch, .. }) => {
+                OptionPatch::Set(value.with_patch(patch))
+            }
+            (
+                OptionPatch::Patch {
+                    patch: lhs,
+                    or_insert: lhs_or_insert,
+                },
+                OptionPatch::Patch {
+                    patch: rhs,
+                    or_insert: rhs_or_insert,
+                },
+            ) => {
+                // When the value was `None`, either it was initialized by `lhs_or_insert` and
+                // is then patched by `rhs`, or it is still `None` for `rhs_or_insert`.
+                let or_insert = match lhs_or_insert {
+                    Some(init) => Some(init.merge(rhs.clone())),
+                    None => rhs_or_insert,
+                };
+                OptionPatch::Patch {
+                    patch: lhs.merge(rhs),
+                    or_insert,
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        match self {
+            OptionPatch::Keep => true,
+            OptionPatch::Patch {
+                patch,
+                or_insert: None,
+            } => patch.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+impl<T, P> Expect<OptionPatch<T, P>> for Option<T>
+where
+    T: Expect<P> + PartialEq,
+{
+    /// `Clear` expects `None`, `Set` expects `Some` with an equal value and `Patch` expects `Some`
+    /// with a value matching the patch.
+    fn mismatches_at(
+        &self,
+        expected: &OptionPatch<T, P>,
+        path: &mut FieldPath,
+        mismatches: &mut Vec<FieldPath>,
+    ) {
+        match (expected, self) {
+            (OptionPatch::Keep, _) | (OptionPatch::Clear, None) => {}
+            (OptionPatch::Set(expected), Some(value)) if value == expected => {}
+            (OptionPatch::Patch { patch, .. }, Some(value)) => {
+                value.mismatches_at(patch, path, mismatches)
+            }
+            _ => mismatches.push(path.clone()),
+        }
+    }
+}
+
+impl<T, P> MergeFrom for OptionPatch<T, P>
+where
+    T: Patch<P> + Default,
+    P: MergeFrom + Clone,
+{
+    fn merge_from(&mut self, rhs: Self) {
+        match (&mut *self, rhs) {
+            (_, OptionPatch::Keep) => {}
+            (
+                OptionPatch::Patch { patch, or_insert },
+                OptionPatch::Patch {
+                    patch: rhs,
+                    or_insert: rhs_or_insert,
+                },
+            ) => {
+                // See `Merge::merge`.
+                match or_insert {
+                    Some(init) => init.merge_from(rhs.clone()),
+                    None => *or_insert = rhs_or_insert,
+                }
+                patch.merge_from(rhs);
+            }
+            (lhs, rhs) => *lhs = std::mem::take(lhs).merge(rhs),
+        }
+    }
+}
+
+impl<T, P> TryMerge for OptionPatch<T, P>
+where
+    T: Patch<P> + Default + PartialEq + Debug + Clone + Send + Sync + 'static,
+    P: TryMerge<Output = P> + Clone + Debug + Send + Sync + 'static,
+{
+    /// Conflicts if both patches are different, unless one of them is `Keep` or both are `Patch`
+    /// without conflicts between the inner patches. The `or_insert` patches are not compared.
+    fn merge_conflicts_at(
+        &self,
+        rhs: &Self,
+        path: &mut FieldPath,
+        conflicts: &mut Vec<MergeConflict>,
+    ) {
+        match (self, rhs) {
+            (OptionPatch::Keep, _) | (_, OptionPatch::Keep) => {}
+            (OptionPatch::Patch { patch: lhs, .. }, OptionPatch::Patch { patch: rhs, .. }) => {
+                lhs.merge_conflicts_at(rhs, path, conflicts)
+            }
+            (OptionPatch::Clear, OptionPatch::Clear) => {}
+            (OptionPatch::Set(lhs), OptionPatch::Set(rhs)) if lhs == rhs => {}
+            (lhs, rhs) => conflicts.push(MergeConflict::new(path.clone(), lhs, rhs)),
+        }
+    }
+}
+
+impl<T, P> Minimize<Option<T>> for OptionPatch<T, P>
+where
+    T: PartialEq,
+    P: Minimize<T>,
+{
+    /// Replaces the no-op variants by `Keep`. The inner patch of `Patch` is minimized and its
+    /// `or_insert` patch is dropped when the value is `Some`.
+    fn minimize_in_place(&mut self, current: &Option<T>) {
+        if let (OptionPatch::Patch { patch, or_insert }, Some(current)) = (&mut *self, current) {
+            patch.minimize_in_place(current);
+            *or_insert = None;
+        }
+        if self.is_noop_for(current) {
+            *self = OptionPatch::Keep;
+        }
+    }
+
+    fn is_noop_for(&self, current: &Option<T>) -> bool {
+        match (self, current) {
+            (OptionPatch::Keep, _) | (OptionPatch::Clear, None) => true,
+            (OptionPatch::Set(value), Some(current)) => value == current,
+            (OptionPatch::Patch { patch, .. }, Some(current)) => patch.is_noop_for(current),
+            (OptionPatch::Patch { or_insert, .. }, None) => or_insert.is_none(),
+            _ => false,
+        }
+    }
+}
+
+impl<T, P> Subtract for OptionPatch<T, P>
+where
+    P: Subtract,
+{
+    /// Returns `Keep` if `rhs` isn't `Keep`, unless both are `Patch` and then the inner patches
+    /// are subtracted.
+    fn subtract(self, rhs: &Self) -> Self {
+        match (self, rhs) {
+            (lhs, OptionPatch::Keep) => lhs,
+            (
+                OptionPatch::Patch { patch, or_insert },
+                OptionPatch::Patch {
+                    patch: rhs,
+                    or_insert: rhs_or_insert,
+                },
+            ) => OptionPatch::Patch {
+                patch: patch.subtract(rhs),
+                or_insert: match (or_insert, rhs_or_insert) {
+                    (Some(init), Some(rhs)) => Some(init.subtract(rhs)),
+                    (or_insert, _) => or_insert,
+                },
+            },
+            _ => OptionPatch::Keep,
+        }
+    }
+}
+
+impl<T, P> Intersect for OptionPatch<T, P>
+where
+    P: Intersect,
+{
+    /// Returns `Keep` if either patch is `Keep`, the inner patches are intersected if both are
+    /// `Patch` and `self` is returned otherwise.
+    fn intersect(self, rhs: &Self) -> Self {
+        match (self, rhs) {
+            (_, OptionPatch::Keep) => OptionPatch::Keep,
+            (
+                OptionPatch::Patch { patch, or_insert },
+                OptionPatch::Patch {
+                    patch: rhs,
+                    or_insert: rhs_or_insert,
+                },
+            ) => OptionPatch::Patch {
+                patch: patch.intersect(rhs),
+                or_insert: match (or_insert, rhs_or_insert) {
+                    (Some(init), Some(rhs)) => Some(init.intersect(rhs)),
+                    _ => None,
+                },
+            },
+            (lhs, _) => lhs,
+        }
+    }
+}
+
+impl<T, P> Restrict for OptionPatch<T, P>
+where
+    P: Restrict,
+{
+    /// Returns `self` if `path` is allowed, otherwise the inner patches of `Patch` are restricted
+    /// and the other variants become `Keep`.
+    fn restrict_at(self, allowed: &[FieldPath], path: &mut FieldPath) -> Self {
+        if path.is_allowed_by(allowed) {
+            return self;
+        }
+        match self {
+            OptionPatch::Patch { patch, or_insert } => OptionPatch::Patch {
+                patch: patch.restrict_at(allowed, path),
+                or_insert: or_insert.map(|init| init.restrict_at(allowed, path)),
+            },
+            _ => OptionPatch::Keep,
+        }
+    }
+}
+
+impl<T, P> Masked for OptionPatch<T, P> {
+    /// The patch is a single field, set unless it's `Keep`.
+    type Mask = bool;
+
+    #[inline]
+    fn mask(&self) -> Self::Mask {
+        !matches!(self, OptionPatch::Keep)
+    }
+
+    #[inline]
+    fn retain(self, mask: Self::Mask) -> Self {
+        if mask {
+            self
+        } else {
+            OptionPatch::Keep
+        }
+    }
+}
+
+impl<T, P> MaskedFrom<Option<T>> for OptionPatch<T, P>
+where
+    T: Clone,
+{
+    /// Builds `Set` or `Clear` from the value if `mask` is `true`, `Keep` otherwise.
+    #[inline]
+    fn from_value_masked(value: &Option<T>, mask: Self::Mask) -> Self {
+        if mask {
+            value.clone().into()
+        } else {
+            OptionPatch::Keep
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl<T, P> proptest::arbitrary::Arbitrary for OptionPatch<T, P>
+where
+    T: proptest::arbitrary::Arbitrary + 'static,
+    P: proptest::arbitrary::Arbitrary + 'static,
+{
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::{arbitrary::any, strategy::LazyJust, strategy::Strategy};
+
+        proptest::prop_oneof![
+            LazyJust::new(|| Self::Keep),
+            LazyJust::new(|| Self::Clear),
+            any::<T>().prop_map(Self::Set),
+            (any::<P>(), any::<Option<P>>())
+                .prop_map(|(patch, or_insert)| Self::Patch { patch, or_insert }),
+        ]
+        .boxed()
+    }
+}
@@ -0,0 +1,95 @@
+use std::{borrow::Cow, fmt};
+
+/// The path of a field inside a value, e.g. `server.tls.cert`.
+///
+/// Each segment is the name of a field, the index of a field of a tuple struct or tuple, or the
+/// index of an element of an array.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FieldPath {
+    segments: Vec<Cow<'static, str>>,
+}
+
+impl FieldPath {
+    /// The path of the value itself, without segments.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a segment to the path.
+    #[inline]
+    pub fn push(&mut self, segment: impl Into<Cow<'static, str>>) {
+        self.segments.push(segment.into());
+    }
+
+    /// Removes the last segment of the path and returns it.
+    #[inline]
+    pub fn pop(&mut self) -> Option<Cow<'static, str>> {
+        self.segments.pop()
+    }
+
+    /// Returns the path with `segment` appended.
+    #[inline]
+    #[must_use]
+    pub fn join(&self, segment: impl Into<Cow<'static, str>>) -> Self {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    /// Returns the segments of the path.
+    #[inline]
+    pub fn segments(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.segments.iter().map(|segment| &**segment)
+    }
+
+    /// Returns `true` if the path has no segments.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns the number of segments of the path.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns `true` if `prefix` is this path or the path of one of its parents.
+    #[inline]
+    pub fn starts_with(&self, prefix: &FieldPath) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+
+    /// Returns `true` if one of the paths of `allowed` starts this path, see [`Restrict`].
+    ///
+    /// [`Restrict`]: crate::Restrict
+    #[inline]
+    pub(crate) fn is_allowed_by(&self, allowed: &[FieldPath]) -> bool {
+        allowed.iter().any(|prefix| self.starts_with(prefix))
+    }
+}
+
+impl<S> FromIterator<S> for FieldPath
+where
+    S: Into<Cow<'static, str>>,
+{
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        Self {
+            segments: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl fmt::Display for FieldPath {
+    /// Formats the segments separated by dots.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            f.write_str(segment)?;
+        }
+        Ok(())
+    }
+}
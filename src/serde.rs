@@ -0,0 +1,289 @@
+//! Deserialization of patches directly into the values they apply to.
+//!
+//! The derive macro implements [`DeserializePatch`] for the target struct with
+//! `#[patch(deserialize_patch)]`. Deserializing into a value walks the incoming map and writes the
+//! value of each present key straight into the matching field, without building the patch struct
+//! first. It behaves like deserializing the patch struct with a derived `Deserialize` and applying
+//! it:
+//!
+//! - the absent fields and the fields set to `null` are left untouched, like the `None` fields of
+//!   the patch,
+//! - the fields with `with` are patched in place by their nested map, and the fields with
+//!   `option_with` or `elementwise` are deserialized as their patch type then applied,
+//! - the unknown keys are ignored, unless the patch struct has `#[serde(deny_unknown_fields)]`,
+//! - a sequence, used by the formats which aren't self-describing, lists the fields in declaration
+//!   order, and it can end before the fields with `#[serde(default)]`.
+//!
+//! The `serde` attributes of the patch struct and its fields set with `patch_attr` rename the
+//! keys like for the patch struct: `rename`, `rename_all`, `alias` and `skip_deserializing` are
+//! supported, and the attributes which change the deserialization otherwise are rejected.
+//!
+//! ```
+//! # use patched::Patch;
+//! #[derive(Patch, Debug, PartialEq)]
+//! #[patch(deserialize_patch)]
+//! struct Window {
+//!     title: String,
+//!     width: u32,
+//!     #[patch(with = PositionPatch)]
+//!     position: Position,
+//! }
+//!
+//! #[derive(Patch, Debug, PartialEq)]
+//! #[patch(deserialize_patch)]
+//! struct Position {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! let mut window = Window {
+//!     title: "a".into(),
+//!     width: 100,
+//!     position: Position { x: 0, y: 0 },
+//! };
+//!
+//! let json = r#"{ "title": null, "width": 200, "position": { "y": 10 } }"#;
+//! let mut deserializer = serde_json::Deserializer::from_str(json);
+//! patched::serde::patch_from(&mut window, &mut deserializer).unwrap();
+//!
+//! assert_eq!(
+//!     window,
+//!     Window {
+//!         title: "a".into(),
+//!         width: 200,
+//!         position: Position { x: 0, y: 10 },
+//!     }
+//! );
+//! ```
+
+use std::{fmt, marker::PhantomData};
+
+use ::serde::de::{
+    DeserializeSeed, Deserializer, Error, IgnoredAny, MapAccess, SeqAccess, Unexpected, Visitor,
+};
+
+/// A value which can be patched by deserializing a patch into it.
+///
+/// Deserializing into the value behaves like deserializing a [`DeserializePatch::Patch`] and
+/// applying it. It's implemented for `Box<T>` when `T` implements it, and by the derive macro for
+/// the target struct with `#[patch(deserialize_patch)]`.
+pub trait DeserializePatch<'de> {
+    /// The patch whose serialized form is deserialized.
+    type Patch;
+
+    /// Deserializes a patch from `deserializer` and applies it to `self`.
+    fn deserialize_patch<D>(&mut self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>;
+}
+
+/// Deserializes a patch from `deserializer` and applies it to `value`.
+///
+/// On error, the fields deserialized before the error have already been written to `value`.
+#[inline]
+pub fn patch_from<'de, T, D>(value: &mut T, deserializer: D) -> Result<(), D::Error>
+where
+    T: DeserializePatch<'de> + ?Sized,
+    D: Deserializer<'de>,
+{
+    value.deserialize_patch(deserializer)
+}
+
+/// A [`DeserializeSeed`] which deserializes a patch into the borrowed value, e.g. to patch a value
+/// from an element of a sequence or a value of a map.
+pub struct PatchSeed<'a, T: ?Sized>(pub &'a mut T);
+
+impl<'de, T> DeserializeSeed<'de> for PatchSeed<'_, T>
+where
+    T: DeserializePatch<'de> + ?Sized,
+{
+    type Value = ();
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize_patch(deserializer)
+    }
+}
+
+impl<'de, T> DeserializePatch<'de> for Box<T>
+where
+    T: DeserializePatch<'de> + ?Sized,
+{
+    type Patch = T::Patch;
+
+    #[inline]
+    fn deserialize_patch<D>(&mut self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        (**self).deserialize_patch(deserializer)
+    }
+}
+
+/// The fields of a struct deriving `Patch`, used by the code generated by the derive macro.
+#[doc(hidden)]
+pub trait PatchFields<'de> {
+    /// The name of the patch struct.
+    const NAME: &'static str;
+
+    /// The names of the fields, in declaration order, without the skipped fields.
+    const FIELDS: &'static [&'static str];
+
+    /// Whether an unknown field is an error.
+    const DENY_UNKNOWN_FIELDS: bool;
+
+    /// Whether each field of `FIELDS` can be missing at the end of a sequence.
+    const DEFAULT_FIELDS: &'static [bool];
+
+    /// Returns the index in `FIELDS` of the field with the name or the alias `name`.
+    fn field_index(name: &str) -> Option<usize>;
+
+    /// Deserializes the next value of `map` into the field at `index` of `FIELDS`.
+    fn patch_field<A>(&mut self, index: usize, map: &mut A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>;
+
+    /// Deserializes the next element of `seq` into the field at `index` of `FIELDS`, returns
+    /// `false` if the sequence has ended.
+    fn patch_element<A>(&mut self, index: usize, seq: &mut A) -> Result<bool, A::Error>
+    where
+        A: SeqAccess<'de>;
+}
+
+/// Deserializes a map of the fields of `value` into it, used by the code generated by the derive
+/// macro.
+#[doc(hidden)]
+pub fn deserialize_fields<'de, T, D>(value: &mut T, deserializer: D) -> Result<(), D::Error>
+where
+    T: PatchFields<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_struct(T::NAME, T::FIELDS, FieldsVisitor(value, PhantomData))
+}
+
+struct FieldsVisitor<'a, 'de, T>(&'a mut T, PhantomData<&'de ()>);
+
+impl<'de, T> Visitor<'de> for FieldsVisitor<'_, 'de, T>
+where
+    T: PatchFields<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "struct {}", T::NAME)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let seed = FieldIndex {
+            fields: T::FIELDS,
+            deny_unknown_fields: T::DENY_UNKNOWN_FIELDS,
+            field_index: T::field_index,
+        };
+        while let Some(index) = map.next_key_seed(seed)? {
+            match index {
+                Some(index) => self.0.patch_field(index, &mut map)?,
+                None => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        for index in 0..T::FIELDS.len() {
+            if !self.0.patch_element(index, &mut seq)? {
+                // Like the derived `Deserialize`, the missing fields must have a default.
+                if T::DEFAULT_FIELDS[index..].iter().all(|default| *default) {
+                    break;
+                }
+                return Err(A::Error::invalid_length(index, &self));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Deserializes a key into the index of the field with this name, `None` for an unknown key.
+#[derive(Clone, Copy)]
+struct FieldIndex {
+    fields: &'static [&'static str],
+    deny_unknown_fields: bool,
+    field_index: fn(&str) -> Option<usize>,
+}
+
+impl FieldIndex {
+    fn unknown<E>(self, name: &str) -> Result<Option<usize>, E>
+    where
+        E: Error,
+    {
+        if self.deny_unknown_fields {
+            Err(E::unknown_field(name, self.fields))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for FieldIndex {
+    type Value = Option<usize>;
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl Visitor<'_> for FieldIndex {
+    type Value = Option<usize>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("field identifier")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match usize::try_from(value) {
+            Ok(index) if index < self.fields.len() => Ok(Some(index)),
+            _ if self.deny_unknown_fields => Err(E::invalid_value(
+                Unexpected::Unsigned(value),
+                &"a field index",
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match (self.field_index)(value) {
+            Some(index) => Ok(Some(index)),
+            None => self.unknown(value),
+        }
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match std::str::from_utf8(value) {
+            Ok(value) => self.visit_str(value),
+            Err(_) => self.unknown(&String::from_utf8_lossy(value)),
+        }
+    }
+}
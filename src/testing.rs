@@ -0,0 +1,155 @@
+//! Checks of the laws that [`Patch`] and [`Merge`] implementations must follow.
+//!
+//! Each function panics with a message describing the values involved if the law doesn't hold,
+//! so they can be called from unit tests or from property tests with generated values. With the
+//! `proptest` feature, `#[patch(arbitrary)]` implements `proptest::arbitrary::Arbitrary` for the
+//! patch struct to generate the patches.
+//!
+//! The laws are checked by observing the patched values, so the patches don't have to implement
+//! `PartialEq`.
+//!
+//! ```
+//! # use patched::{testing, Patch};
+//! #[derive(Patch, Clone, Debug, PartialEq)]
+//! #[patch(from, setters)]
+//! #[patch_attr(derive(Clone, Debug))]
+//! struct Foo {
+//!     a: u8,
+//!     b: String,
+//! }
+//!
+//! let value = Foo { a: 1, b: "b".into() };
+//! let patches = [
+//!     FooPatch::default().a(2),
+//!     FooPatch::default().b("c"),
+//!     FooPatch::default().a(3).b("d"),
+//! ];
+//!
+//! testing::check_laws(&value, &patches);
+//! testing::check_from::<_, FooPatch>(&value, Foo { a: 0, b: String::new() });
+//! ```
+
+use std::fmt::Debug;
+
+use crate::{Merge, MergeFrom, Patch};
+
+/// Checks that merging two patches then applying the result is the same as applying them one
+/// after the other.
+#[track_caller]
+pub fn check_coherence<T, P>(value: &T, lhs: P, rhs: P)
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: Merge<Output = P> + Clone + Debug,
+{
+    let sequential = value
+        .clone()
+        .with_patch(lhs.clone())
+        .with_patch(rhs.clone());
+    let merged = value.clone().with_patch(lhs.clone().merge(rhs.clone()));
+
+    assert_eq!(
+        sequential, merged,
+        "`merge` is not coherent with `patch`\n value: {value:?}\n   lhs: {lhs:?}\n   rhs: {rhs:?}",
+    );
+}
+
+/// Checks that merging `rhs` into `lhs` in place is the same as merging them.
+#[track_caller]
+pub fn check_merge_from<T, P>(value: &T, lhs: P, rhs: P)
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: MergeFrom + Clone + Debug,
+{
+    let mut in_place = lhs.clone();
+    in_place.merge_from(rhs.clone());
+    let merged = lhs.clone().merge(rhs.clone());
+
+    assert_eq!(
+        value.clone().with_patch(in_place),
+        value.clone().with_patch(merged),
+        "`merge_from` is not coherent with `merge`\n value: {value:?}\n   lhs: {lhs:?}\n   rhs: {rhs:?}",
+    );
+}
+
+/// Checks that `merge` is associative: `(a.merge(b)).merge(c)` applies like `a.merge(b.merge(c))`.
+#[track_caller]
+pub fn check_associativity<T, P>(value: &T, a: P, b: P, c: P)
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: Merge<Output = P> + Clone + Debug,
+{
+    let left = value
+        .clone()
+        .with_patch(a.clone().merge(b.clone()).merge(c.clone()));
+    let right = value
+        .clone()
+        .with_patch(a.clone().merge(b.clone().merge(c.clone())));
+
+    assert_eq!(
+        left, right,
+        "`merge` is not associative\n value: {value:?}\n     a: {a:?}\n     b: {b:?}\n     c: {c:?}",
+    );
+}
+
+/// Checks that the default patch is an identity: it doesn't change a value and it doesn't change
+/// a patch it's merged with, on either side.
+#[track_caller]
+pub fn check_default_identity<T, P>(value: &T, patch: P)
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: Merge<Output = P> + Default + Clone + Debug,
+{
+    assert_eq!(
+        value.clone().with_patch(P::default()),
+        *value,
+        "the default patch is not an identity\n value: {value:?}",
+    );
+
+    let expected = value.clone().with_patch(patch.clone());
+    assert_eq!(
+        value.clone().with_patch(P::default().merge(patch.clone())),
+        expected,
+        "the default patch is not a left identity of `merge`\n value: {value:?}\n patch: {patch:?}",
+    );
+    assert_eq!(
+        value.clone().with_patch(patch.clone().merge(P::default())),
+        expected,
+        "the default patch is not a right identity of `merge`\n value: {value:?}\n patch: {patch:?}",
+    );
+}
+
+/// Checks that the patch converted from `value` turns any `other` value into `value`.
+#[track_caller]
+pub fn check_from<T, P>(value: &T, other: T)
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: From<T>,
+{
+    let patched = other.clone().with_patch(P::from(value.clone()));
+
+    assert_eq!(
+        patched, *value,
+        "the patch converted from a value doesn't round-trip\n value: {value:?}\n other: {other:?}",
+    );
+}
+
+/// Runs [`check_coherence`], [`check_associativity`] and [`check_default_identity`] for every
+/// combination of `patches`.
+#[track_caller]
+pub fn check_laws<T, P>(value: &T, patches: &[P])
+where
+    T: Patch<P> + Clone + PartialEq + Debug,
+    P: Merge<Output = P> + Default + Clone + Debug,
+{
+    for a in patches {
+        check_default_identity(value, a.clone());
+
+        for b in patches {
+            check_coherence(value, a.clone(), b.clone());
+
+            for c in patches {
+                check_associativity(value, a.clone(), b.clone(), c.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{any::Any, error::Error, fmt, sync::Arc};
+
+use crate::{FieldPath, Merge, Minimize};
+
+/// A [`Merge`] that fails when both patches set the same field to different values, instead of
+/// letting `rhs` win.
+///
+/// It's implemented by the derive macro with `#[patch(try_merge)]` for the patch struct, recursing
+/// into nested patches. The conflicts keep both values, so the values of the fields must be
+/// `Clone`, `Send`, `Sync` and `'static`.
+///
+/// ```
+/// # use patched::{Patch, TryMerge};
+/// #[derive(Patch)]
+/// #[patch(try_merge, minimize, setters)]
+/// #[patch_attr(derive(Debug, Clone))]
+/// struct Record {
+///     name: String,
+///     email: String,
+/// }
+///
+/// let alice = RecordPatch::default().name("Alice");
+/// let bob = RecordPatch::default().name("Bob").email("bob@example.com");
+///
+/// let error = alice.clone().try_merge(bob.clone()).unwrap_err();
+/// let conflict = &error.conflicts()[0];
+/// assert_eq!(conflict.path().to_string(), "name");
+/// assert_eq!(conflict.left_value::<String>().unwrap(), "Alice");
+/// assert_eq!(conflict.right_value::<String>().unwrap(), "Bob");
+/// assert_eq!(conflict.to_string(), "`name` is set to \"Alice\" and \"Bob\"");
+///
+/// // With the common base of both patches, a field set to its current value doesn't conflict.
+/// let base = Record { name: "Alice".into(), email: String::new() };
+/// let merged = alice.try_merge3(bob, &base).unwrap();
+/// assert_eq!(merged.name.as_deref(), Some("Bob"));
+/// ```
+pub trait TryMerge<Rhs = Self>: Merge<Rhs> {
+    /// Pushes to `conflicts` each field set to different values by `self` and `rhs`, `path` being
+    /// the path of `self`.
+    fn merge_conflicts_at(
+        &self,
+        rhs: &Rhs,
+        path: &mut FieldPath,
+        conflicts: &mut Vec<MergeConflict>,
+    );
+
+    /// Returns the fields set to different values by `self` and `rhs`.
+    #[inline]
+    fn merge_conflicts(&self, rhs: &Rhs) -> Vec<MergeConflict> {
+        let mut conflicts = Vec::new();
+        self.merge_conflicts_at(rhs, &mut FieldPath::new(), &mut conflicts);
+        conflicts
+    }
+
+    /// Merges `self` and `rhs`, or returns the conflicting fields if they set the same field to
+    /// different values.
+    #[inline]
+    fn try_merge(self, rhs: Rhs) -> Result<Self::Output, MergeError>
+    where
+        Self: Sized,
+    {
+        let conflicts = self.merge_conflicts(&rhs);
+        if !conflicts.is_empty() {
+            return Err(MergeError { conflicts });
+        }
+
+        Ok(self.merge(rhs))
+    }
+
+    /// Merges `self` and `rhs`, two patches of `base`, or returns the conflicting fields.
+    ///
+    /// It's a three-way merge: the fields a patch sets to their values in `base` are dropped, so
+    /// only the fields that both patches change to different values conflict.
+    #[inline]
+    fn try_merge3<T>(self, rhs: Rhs, base: &T) -> Result<Self::Output, MergeError>
+    where
+        Self: Minimize<T> + Sized,
+        Rhs: Minimize<T>,
+    {
+        self.minimize(base).try_merge(rhs.minimize(base))
+    }
+}
+
+impl<T> TryMerge for Option<T>
+where
+    T: PartialEq + fmt::Debug + Clone + Send + Sync + 'static,
+{
+    /// Conflicts if both values are `Some` and different.
+    #[inline]
+    fn merge_conflicts_at(
+        &self,
+        rhs: &Self,
+        path: &mut FieldPath,
+        conflicts: &mut Vec<MergeConflict>,
+    ) {
+        if let (Some(left), Some(right)) = (self, rhs) {
+            if left != right {
+                conflicts.push(MergeConflict::new(path.clone(), left, right));
+            }
+        }
+    }
+}
+
+/// A field set to different values by two merged patches.
+///
+/// It keeps both values, which can be downcast to the type of the field, and their `Debug`
+/// representations.
+#[derive(Clone)]
+pub struct MergeConflict {
+    path: FieldPath,
+    left: Arc<dyn Any + Send + Sync>,
+    right: Arc<dyn Any + Send + Sync>,
+    left_debug: String,
+    right_debug: String,
+}
+
+impl MergeConflict {
+    /// Creates a conflict of the field at `path` between the values `left` and `right`.
+    pub fn new<T>(path: FieldPath, left: &T, right: &T) -> Self
+    where
+        T: fmt::Debug + Clone + Send + Sync + 'static,
+    {
+        Self {
+            path,
+            left: Arc::new(left.clone()),
+            right: Arc::new(right.clone()),
+            left_debug: format!("{left:?}"),
+            right_debug: format!("{right:?}"),
+        }
+    }
+
+    /// Returns the path of the field.
+    #[inline]
+    pub fn path(&self) -> &FieldPath {
+        &self.path
+    }
+
+    /// Returns the value set by the left patch, if it's of type `T`.
+    #[inline]
+    pub fn left_value<T: Any>(&self) -> Option<&T> {
+        self.left.downcast_ref()
+    }
+
+    /// Returns the value set by the right patch, if it's of type `T`.
+    #[inline]
+    pub fn right_value<T: Any>(&self) -> Option<&T> {
+        self.right.downcast_ref()
+    }
+
+    /// Returns the `Debug` representation of the value set by the left patch.
+    #[inline]
+    pub fn left(&self) -> &str {
+        &self.left_debug
+    }
+
+    /// Returns the `Debug` representation of the value set by the right patch.
+    #[inline]
+    pub fn right(&self) -> &str {
+        &self.right_debug
+    }
+}
+
+impl fmt::Debug for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeConflict")
+            .field("path", &self.path)
+            .field("left", &format_args!("{}", self.left_debug))
+            .field("right", &format_args!("{}", self.right_debug))
+            .finish()
+    }
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is set to {} and {}",
+            self.path, self.left_debug, self.right_debug
+        )
+    }
+}
+
+/// The error returned by [`TryMerge::try_merge`].
+#[derive(Debug, Clone)]
+pub struct MergeError {
+    conflicts: Vec<MergeConflict>,
+}
+
+impl MergeError {
+    /// Returns the conflicting fields.
+    #[inline]
+    pub fn conflicts(&self) -> &[MergeConflict] {
+        &self.conflicts
+    }
+
+    /// Consumes the error and returns the conflicting fields.
+    #[inline]
+    pub fn into_conflicts(self) -> Vec<MergeConflict> {
+        self.conflicts
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("conflicting patches: ")?;
+        for (i, conflict) in self.conflicts.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MergeError {}
@@ -0,0 +1,664 @@
+//! A compact binary encoding of patches.
+//!
+//! The derive macro implements [`Wire`] and [`WireField`] for the patch struct, when the types of
+//! its fields implement them. A patch is encoded as:
+//!
+//! - the number of fields of the patch struct,
+//! - a bitmap with a bit set for each field set by the patch,
+//! - the length in bytes of the values,
+//! - the values of the set fields, in declaration order. A nested patch is encoded the same way.
+//!
+//! The encoding isn't self-describing, so the encoder and the decoder must agree on the types.
+//! To stay compatible, new fields must only be appended to a struct, and existing fields must not
+//! be removed, reordered or change type. Then:
+//!
+//! - a patch from an older version is decoded with the appended fields unset,
+//! - a patch from a newer version is decoded without the values of the appended fields, which are
+//!   skipped thanks to the length of the values.
+//!
+//! The integers are encoded as LEB128 varints, zigzag encoded for the signed ones, except `u8`
+//! and `i8` which are encoded as a single byte.
+//!
+//! ```
+//! # use patched::{wire, Patch};
+//! #[derive(Patch)]
+//! #[patch(setters)]
+//! #[patch_attr(derive(Debug, PartialEq))]
+//! struct Window {
+//!     title: String,
+//!     width: u32,
+//! }
+//!
+//! let patch = WindowPatch::default().width(200);
+//! let bytes = wire::encode(&patch);
+//! assert_eq!(bytes, [2, 0b10, 2, 200, 1]);
+//! assert_eq!(wire::decode::<WindowPatch>(&bytes), Ok(patch));
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::OptionPatch;
+
+/// Encodes `value`.
+pub fn encode<T>(value: &T) -> Vec<u8>
+where
+    T: Wire,
+{
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+/// Decodes a value from all the bytes of `bytes`.
+pub fn decode<T>(mut bytes: &[u8]) -> Result<T, DecodeError>
+where
+    T: Wire,
+{
+    let value = T::decode(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+/// The error returned when decoding invalid bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The bytes end in the middle of a value.
+    UnexpectedEnd,
+    /// The bytes of a value of this type are invalid.
+    InvalidValue(&'static str),
+    /// There are bytes left after the value.
+    TrailingBytes,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => f.write_str("unexpected end of input"),
+            DecodeError::InvalidValue(ty) => write!(f, "invalid value of type `{ty}`"),
+            DecodeError::TrailingBytes => f.write_str("trailing bytes after the value"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// A value with a binary encoding.
+pub trait Wire: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from the start of `input` and advances it past the value.
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// The type of a field of a patch, which may be unset.
+///
+/// It's implemented for `Option<T>`, [`OptionPatch`], tuples and arrays of fields for
+/// `elementwise` fields, and by the derive macro for the patch struct.
+pub trait WireField: Sized {
+    /// Returns `true` if the field is set, and then its value is encoded.
+    fn is_set(&self) -> bool;
+
+    /// Appends the encoding of the value of the field, which is set.
+    fn encode_set(&self, out: &mut Vec<u8>);
+
+    /// Decodes the value of a set field.
+    fn decode_set(input: &mut &[u8]) -> Result<Self, DecodeError>;
+
+    /// Returns the unset field.
+    fn unset() -> Self;
+}
+
+/// Encodes the fields of a patch: their number, the bitmap of the set fields and their values.
+///
+/// ```
+/// # use patched::wire::{FieldsEncoder, Wire};
+/// let mut out = Vec::new();
+/// let mut fields = FieldsEncoder::new(&mut out, 2);
+/// fields.field(&None::<u8>);
+/// fields.field(&Some(true));
+/// fields.finish();
+/// assert_eq!(out, [2, 0b10, 1, 1]);
+/// ```
+pub struct FieldsEncoder<'a> {
+    out: &'a mut Vec<u8>,
+    bitmap: usize,
+    index: usize,
+    values: Vec<u8>,
+}
+
+impl<'a> FieldsEncoder<'a> {
+    /// Starts the encoding of `count` fields.
+    pub fn new(out: &'a mut Vec<u8>, count: usize) -> Self {
+        count.encode(out);
+        let bitmap = out.len();
+        out.resize(bitmap + count.div_ceil(8), 0);
+        Self {
+            out,
+            bitmap,
+            index: 0,
+            values: Vec::new(),
+        }
+    }
+
+    /// Encodes the next field.
+    pub fn field<F: WireField>(&mut self, field: &F) {
+        if field.is_set() {
+            self.set_bit();
+            field.encode_set(&mut self.values);
+        }
+        self.index += 1;
+    }
+
+    /// Encodes the next field, set to `value` if it's `Some`.
+    pub fn value<T: Wire>(&mut self, value: Option<&T>) {
+        if let Some(value) = value {
+            self.set_bit();
+            value.encode(&mut self.values);
+        }
+        self.index += 1;
+    }
+
+    /// Appends the values of the fields.
+    pub fn finish(self) {
+        self.values.len().encode(self.out);
+        self.out.extend_from_slice(&self.values);
+    }
+
+    fn set_bit(&mut self) {
+        self.out[self.bitmap + self.index / 8] |= 1 << (self.index % 8);
+    }
+}
+
+/// Decodes the fields of a patch encoded by [`FieldsEncoder`].
+pub struct FieldsDecoder<'a> {
+    count: usize,
+    bitmap: &'a [u8],
+    index: usize,
+    values: &'a [u8],
+}
+
+impl<'a> FieldsDecoder<'a> {
+    /// Starts the decoding of the fields at the start of `input`, and advances it past them.
+    pub fn new(input: &mut &'a [u8]) -> Result<Self, DecodeError> {
+        let count = usize::decode(input)?;
+        let bitmap = take(input, count.div_ceil(8))?;
+        let len = usize::decode(input)?;
+        let values = take(input, len)?;
+        Ok(Self {
+            count,
+            bitmap,
+            index: 0,
+            values,
+        })
+    }
+
+    /// Decodes the next field, unset if it's not in the encoding.
+    pub fn field<F: WireField>(&mut self) -> Result<F, DecodeError> {
+        if self.next_bit() {
+            F::decode_set(&mut self.values)
+        } else {
+            Ok(F::unset())
+        }
+    }
+
+    /// Decodes the next field, `None` if it's unset or not in the encoding.
+    pub fn value<T: Wire>(&mut self) -> Result<Option<T>, DecodeError> {
+        if self.next_bit() {
+            T::decode(&mut self.values).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Ends the decoding, the values of the fields appended to a newer version of the patch are
+    /// ignored. Without such values, the values of the known fields must take all the length of
+    /// the values, otherwise there are trailing bytes. The values of the appended fields aren't
+    /// checked since their types are unknown.
+    pub fn finish(self) -> Result<(), DecodeError> {
+        let appended = (self.index..self.count).any(|index| self.bit(index));
+        if !appended && !self.values.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(())
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let index = self.index;
+        self.index += 1;
+        self.bit(index)
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        index < self.count && self.bitmap[index / 8] & (1 << (index % 8)) != 0
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn encode_varint(mut value: u128, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn decode_varint(input: &mut &[u8], bits: u32, ty: &'static str) -> Result<u128, DecodeError> {
+    let mut value = 0u128;
+    let mut shift = 0;
+    loop {
+        let [byte, rest @ ..] = *input else {
+            return Err(DecodeError::UnexpectedEnd);
+        };
+        *input = rest;
+        let chunk = u128::from(byte & 0x7f);
+        if shift >= bits || (bits - shift < 7 && chunk >> (bits - shift) != 0) {
+            return Err(DecodeError::InvalidValue(ty));
+        }
+        value |= chunk << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+macro_rules! impl_unsigned {
+    ($($ty:ident)*) => {$(
+        impl Wire for $ty {
+            #[inline]
+            fn encode(&self, out: &mut Vec<u8>) {
+                encode_varint(*self as u128, out);
+            }
+
+            #[inline]
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let value = decode_varint(input, $ty::BITS, stringify!($ty))?;
+                Ok(value as $ty)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u16 u32 u64 u128 usize);
+
+macro_rules! impl_signed {
+    ($($ty:ident $unsigned:ident)*) => {$(
+        impl Wire for $ty {
+            #[inline]
+            fn encode(&self, out: &mut Vec<u8>) {
+                let zigzag = (*self << 1) ^ (*self >> ($ty::BITS - 1));
+                (zigzag as $unsigned).encode(out);
+            }
+
+            #[inline]
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let zigzag = $unsigned::decode(input)?;
+                Ok((zigzag >> 1) as $ty ^ -((zigzag & 1) as $ty))
+            }
+        }
+    )*};
+}
+
+impl_signed!(i16 u16 i32 u32 i64 u64 i128 u128 isize usize);
+
+impl Wire for u8 {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(take(input, 1)?[0])
+    }
+}
+
+impl Wire for i8 {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(u8::decode(input)? as i8)
+    }
+}
+
+impl Wire for bool {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidValue("bool")),
+        }
+    }
+}
+
+impl Wire for char {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self).encode(out);
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        char::from_u32(u32::decode(input)?).ok_or(DecodeError::InvalidValue("char"))
+    }
+}
+
+impl Wire for f32 {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let bytes = take(input, 4)?;
+        Ok(f32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl Wire for f64 {
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let bytes = take(input, 8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl Wire for () {
+    #[inline]
+    fn encode(&self, _: &mut Vec<u8>) {}
+
+    #[inline]
+    fn decode(_: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl Wire for String {
+    /// Encodes the length in bytes followed by the UTF-8 bytes.
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = usize::decode(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidValue("String"))
+    }
+}
+
+impl<T> Wire for Vec<T>
+where
+    T: Wire,
+{
+    /// Encodes the number of elements followed by the elements.
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for value in self {
+            value.encode(out);
+        }
+    }
+
+    /// A `Vec` of zero-sized values, which may take no bytes, is invalid if it has more than
+    /// 65536 elements, so a corrupted length can't make the decoding loop for a long time.
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        const MAX_ZERO_SIZED_LEN: usize = 1 << 16;
+
+        let len = usize::decode(input)?;
+        if std::mem::size_of::<T>() == 0 && len > MAX_ZERO_SIZED_LEN {
+            return Err(DecodeError::InvalidValue("Vec"));
+        }
+        // Each element takes at least one byte, except zero-sized ones.
+        let mut values = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            values.push(T::decode(input)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T> Wire for Box<T>
+where
+    T: Wire,
+{
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out);
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        T::decode(input).map(Box::new)
+    }
+}
+
+impl<T> Wire for Option<T>
+where
+    T: Wire,
+{
+    /// Encodes `0` for `None`, and `1` followed by the value for `Some`.
+    #[inline]
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.is_some().encode(out);
+        if let Some(value) = self {
+            value.encode(out);
+        }
+    }
+
+    #[inline]
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match bool::decode(input)? {
+            true => T::decode(input).map(Some),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<T, const N: usize> Wire for [T; N]
+where
+    T: Wire,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        for value in self {
+            value.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let values = (0..N)
+            .map(|_| T::decode(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.try_into().ok().unwrap())
+    }
+}
+
+impl<T> WireField for Option<T>
+where
+    T: Wire,
+{
+    #[inline]
+    fn is_set(&self) -> bool {
+        self.is_some()
+    }
+
+    #[inline]
+    fn encode_set(&self, out: &mut Vec<u8>) {
+        if let Some(value) = self {
+            value.encode(out);
+        }
+    }
+
+    #[inline]
+    fn decode_set(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        T::decode(input).map(Some)
+    }
+
+    #[inline]
+    fn unset() -> Self {
+        None
+    }
+}
+
+impl<T, P> WireField for OptionPatch<T, P>
+where
+    T: Wire,
+    P: Wire,
+{
+    #[inline]
+    fn is_set(&self) -> bool {
+        !matches!(self, OptionPatch::Keep)
+    }
+
+    /// Encodes `0` for `Clear`, `1` followed by the value for `Set`, and `2` followed by the
+    /// patch and the optional `or_insert` patch for `Patch`.
+    fn encode_set(&self, out: &mut Vec<u8>) {
+        match self {
+            OptionPatch::Keep => {}
+            OptionPatch::Clear => out.push(0),
+            OptionPatch::Set(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+            OptionPatch::Patch { patch, or_insert } => {
+                out.push(2);
+                patch.encode(out);
+                or_insert.encode(out);
+            }
+        }
+    }
+
+    fn decode_set(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(OptionPatch::Clear),
+            1 => T::decode(input).map(OptionPatch::Set),
+            2 => Ok(OptionPatch::Patch {
+                patch: P::decode(input)?,
+                or_insert: Option::decode(input)?,
+            }),
+            _ => Err(DecodeError::InvalidValue("OptionPatch")),
+        }
+    }
+
+    #[inline]
+    fn unset() -> Self {
+        OptionPatch::Keep
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name),+> Wire for ($($name,)+)
+        where
+            $($name: Wire,)+
+        {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $(self.$index.encode(out);)+
+            }
+
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                Ok(($($name::decode(input)?,)+))
+            }
+        }
+
+        impl<$($name),+> WireField for ($($name,)+)
+        where
+            $($name: WireField,)+
+        {
+            /// The elements are encoded like the fields of a patch.
+            fn is_set(&self) -> bool {
+                $(self.$index.is_set())||+
+            }
+
+            fn encode_set(&self, out: &mut Vec<u8>) {
+                let mut fields = FieldsEncoder::new(out, [$($index),+].len());
+                $(fields.field(&self.$index);)+
+                fields.finish();
+            }
+
+            fn decode_set(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let mut fields = FieldsDecoder::new(input)?;
+                let value = ($(fields.field::<$name>()?,)+);
+                fields.finish()?;
+                Ok(value)
+            }
+
+            fn unset() -> Self {
+                ($($name::unset(),)+)
+            }
+        }
+    };
+}
+
+impl_tuple!(A 0);
+impl_tuple!(A 0, B 1);
+impl_tuple!(A 0, B 1, C 2);
+impl_tuple!(A 0, B 1, C 2, D 3);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+impl<F, const N: usize> WireField for [F; N]
+where
+    F: WireField,
+{
+    /// The elements are encoded like the fields of a patch.
+    fn is_set(&self) -> bool {
+        self.iter().any(F::is_set)
+    }
+
+    fn encode_set(&self, out: &mut Vec<u8>) {
+        let mut fields = FieldsEncoder::new(out, N);
+        for field in self {
+            fields.field(field);
+        }
+        fields.finish();
+    }
+
+    fn decode_set(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let mut fields = FieldsDecoder::new(input)?;
+        let values = (0..N)
+            .map(|_| fields.field())
+            .collect::<Result<Vec<F>, _>>()?;
+        fields.finish()?;
+        Ok(values.try_into().ok().unwrap())
+    }
+
+    fn unset() -> Self {
+        std::array::from_fn(|_| F::unset())
+    }
+}
@@ -1,6 +1,32 @@
 use patched::Merge as _;
 use patched::Patch;
 
+/// The nested struct of the tests of the patch operations.
+#[derive(Patch, Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "proptest", patch(arbitrary))]
+#[patch(
+    setters, from, expect, try_merge, notify, by_ref, try_into, minimize, algebra, mask
+)]
+#[patch_attr(derive(Debug, PartialEq, Clone))]
+struct Goo {
+    a: u8,
+    b: String,
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for Goo {
+    type Parameters = ();
+    type Strategy = proptest::strategy::BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        use proptest::strategy::Strategy;
+
+        proptest::arbitrary::any::<GooPatch>()
+            .prop_map(|patch| Goo::default().with_patch(patch))
+            .boxed()
+    }
+}
+
 #[test]
 fn patch() {
     #[derive(Patch, PartialEq, Eq, Debug)]
@@ -199,3 +225,1864 @@ fn merge() {
         }
     );
 }
+
+#[cfg(feature = "schemars")]
+#[test]
+fn json_schema() {
+    /// A foo.
+    #[derive(Patch)]
+    #[patch(name = FooPatch, json_schema)]
+    struct _Foo {
+        /// The `a` field.
+        a: u32,
+        /// The `b` field.
+        #[patch(with = GooPatch)]
+        b: _Goo,
+    }
+
+    #[derive(Patch)]
+    #[patch(name = GooPatch, json_schema)]
+    struct _Goo {
+        c: String,
+    }
+
+    let schema = schemars::schema_for!(FooPatch);
+
+    assert_eq!(
+        schema.as_value(),
+        &serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "FooPatch",
+            "description": "A foo.",
+            "type": "object",
+            "properties": {
+                "a": {
+                    "description": "The `a` field.",
+                    "type": ["integer", "null"],
+                    "format": "uint32",
+                    "minimum": 0
+                },
+                "b": {
+                    "description": "The `b` field.",
+                    "$ref": "#/$defs/GooPatch"
+                }
+            },
+            "$defs": {
+                "GooPatch": {
+                    "type": "object",
+                    "properties": {
+                        "c": { "type": ["string", "null"] }
+                    }
+                }
+            }
+        })
+    );
+
+    /// A tuple.
+    #[derive(Patch)]
+    #[patch(name = TuplePatch, json_schema)]
+    struct _Tuple(u32, #[patch(with = GooPatch)] _Goo);
+
+    let schema = schemars::schema_for!(TuplePatch);
+
+    assert_eq!(
+        schema.as_value()["prefixItems"],
+        serde_json::json!([
+            { "type": ["integer", "null"], "format": "uint32", "minimum": 0 },
+            { "$ref": "#/$defs/GooPatch" }
+        ])
+    );
+}
+
+#[test]
+fn cfg_fields() {
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch_attr(derive(Debug, PartialEq, Eq))]
+    #[patch(from)]
+    struct Foo {
+        a: u32,
+        #[cfg(any())]
+        b: NotPatchable,
+        #[cfg(not(any()))]
+        c: String,
+    }
+
+    #[cfg(any())]
+    struct NotPatchable;
+
+    let value = Foo {
+        a: 5,
+        c: String::from("Hello"),
+    }
+    .with_patch(FooPatch {
+        c: Some(String::from("World")),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        FooPatch::from(value),
+        FooPatch {
+            a: Some(5),
+            c: Some(String::from("World")),
+        }
+    );
+}
+
+#[test]
+fn field_visibility() {
+    mod inner {
+        use patched::Patch;
+
+        #[derive(Patch, Debug, PartialEq, Eq)]
+        #[patch(field_vis = inherit)]
+        pub struct Foo {
+            pub a: u32,
+            b: String,
+            #[patch(vis = "pub")]
+            c: bool,
+        }
+
+        impl Foo {
+            pub fn new(a: u32, b: String, c: bool) -> Self {
+                Self { a, b, c }
+            }
+        }
+    }
+
+    use inner::{Foo, FooPatch};
+
+    // The fields which aren't `pub` on the patch struct get a setter without `setters`.
+    let mut patch = FooPatch::default().b("World");
+    patch.a = Some(10);
+    patch.c = Some(true);
+
+    assert_eq!(
+        Foo::new(5, String::from("Hello"), false).with_patch(patch),
+        Foo::new(10, String::from("World"), true),
+    );
+}
+
+#[test]
+fn setters() {
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch(setters)]
+    struct Foo {
+        a: u32,
+        b: String,
+        #[patch(with = GooPatch)]
+        c: Goo,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch(setters)]
+    struct Goo {
+        a: bool,
+        b: u8,
+    }
+
+    let value = Foo {
+        a: 1,
+        b: String::from("Hello"),
+        c: Goo { a: false, b: 2 },
+    };
+
+    let patch = FooPatch::default().a(10).b("World").c(|patch| patch.b(20));
+
+    assert_eq!(
+        value.with_patch(patch),
+        Foo {
+            a: 10,
+            b: String::from("World"),
+            c: Goo { a: false, b: 20 },
+        }
+    );
+}
+
+#[test]
+fn patch_macro() {
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch_attr(derive(Debug, PartialEq, Eq))]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        b: Goo,
+        c: Option<String>,
+        d: bool,
+    }
+
+    #[derive(Patch, Debug, Default, PartialEq, Eq)]
+    #[patch_attr(derive(Clone, Debug, PartialEq, Eq))]
+    struct Goo {
+        a: bool,
+        b: u8,
+    }
+
+    assert_eq!(
+        patched::patch!(Foo {
+            a: 1,
+            b.b: 2,
+            c: Some(String::from("Hello")),
+        }),
+        FooPatch {
+            a: Some(1),
+            b: GooPatch {
+                a: None,
+                b: Some(2),
+            },
+            c: Some(Some(String::from("Hello"))),
+            d: None,
+        }
+    );
+
+    assert_eq!(
+        patched::patch!(Foo { d: true, c: null }),
+        FooPatch {
+            a: None,
+            b: GooPatch::default(),
+            c: Some(None),
+            d: Some(true),
+        }
+    );
+
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch_attr(derive(Debug, PartialEq, Eq))]
+    struct Hoo {
+        #[patch(option_with = GooPatch)]
+        a: Option<Goo>,
+        #[patch(option_with = GooPatch)]
+        b: Option<Goo>,
+    }
+
+    assert_eq!(
+        patched::patch!(Hoo {
+            a: Some(Goo { a: true, b: 1 }),
+            b: null,
+        }),
+        HooPatch {
+            a: patched::OptionPatch::Set(Goo { a: true, b: 1 }),
+            b: patched::OptionPatch::Clear,
+        }
+    );
+}
+
+#[test]
+fn remote() {
+    mod other {
+        #[derive(Debug, PartialEq, Eq)]
+        pub struct Config {
+            pub a: u32,
+            pub b: String,
+            pub c: bool,
+        }
+    }
+
+    #[derive(Patch)]
+    #[patch(remote = other::Config, from, setters)]
+    #[patch_attr(derive(Debug, PartialEq, Eq))]
+    #[allow(dead_code)]
+    struct ConfigDef {
+        a: u32,
+        b: String,
+    }
+
+    let mut value = other::Config {
+        a: 1,
+        b: String::from("Hello"),
+        c: true,
+    };
+
+    value.patch(ConfigPatch::default().b("World"));
+
+    assert_eq!(
+        value,
+        other::Config {
+            a: 1,
+            b: String::from("World"),
+            c: true,
+        }
+    );
+
+    assert_eq!(
+        ConfigPatch::from(value),
+        ConfigPatch {
+            a: Some(1),
+            b: Some(String::from("World")),
+        }
+    );
+}
+
+#[test]
+fn patch_smart_pointers() {
+    use std::{borrow::Cow, cell::RefCell, rc::Rc, sync::Arc};
+
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch(setters)]
+    struct Foo {
+        #[patch(with = GooPatch)]
+        a: Box<Goo>,
+        #[patch(with = GooPatch)]
+        b: Rc<Goo>,
+        #[patch(with = GooPatch)]
+        c: Arc<Goo>,
+        #[patch(with = GooPatch)]
+        d: Cow<'static, Goo>,
+        #[patch(with = GooPatch)]
+        e: RefCell<Goo>,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Eq, Clone)]
+    #[patch(setters)]
+    struct Goo {
+        a: u8,
+        b: u8,
+    }
+
+    const GOO: Goo = Goo { a: 1, b: 2 };
+
+    let shared = Rc::new(GOO);
+
+    let value = Foo {
+        a: Box::new(GOO),
+        b: Rc::clone(&shared),
+        c: Arc::new(GOO),
+        d: Cow::Borrowed(&GOO),
+        e: RefCell::new(GOO),
+    }
+    .with_patch(
+        FooPatch::default()
+            .a(|p| p.a(10))
+            .b(|p| p.a(20))
+            .c(|p| p.a(30))
+            .d(|p| p.a(40))
+            .e(|p| p.a(50)),
+    );
+
+    assert_eq!(
+        value,
+        Foo {
+            a: Box::new(Goo { a: 10, b: 2 }),
+            b: Rc::new(Goo { a: 20, b: 2 }),
+            c: Arc::new(Goo { a: 30, b: 2 }),
+            d: Cow::Owned(Goo { a: 40, b: 2 }),
+            e: RefCell::new(Goo { a: 50, b: 2 }),
+        }
+    );
+
+    // The shared value is cloned before being patched.
+    assert_eq!(*shared, GOO);
+
+    // An empty nested patch doesn't clone the shared values.
+    let arc = Arc::new(GOO);
+    let value = Foo {
+        a: Box::new(GOO),
+        b: Rc::clone(&shared),
+        c: Arc::clone(&arc),
+        d: Cow::Borrowed(&GOO),
+        e: RefCell::new(GOO),
+    }
+    .with_patch(FooPatch::default().a(|p| p.a(10)));
+
+    assert!(Rc::ptr_eq(&value.b, &shared));
+    assert!(Arc::ptr_eq(&value.c, &arc));
+    assert!(matches!(value.d, Cow::Borrowed(_)));
+}
+
+#[test]
+fn option_patch() {
+    use patched::OptionPatch;
+
+    #[derive(Patch, Debug, PartialEq, Eq)]
+    #[patch(setters)]
+    struct Server {
+        port: u16,
+        #[patch(option_with = TlsPatch)]
+        tls: Option<Tls>,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Eq, Clone, Default)]
+    #[patch(setters)]
+    #[patch_attr(derive(Debug, Clone, PartialEq))]
+    struct Tls {
+        cert: String,
+        key: String,
+    }
+
+    let tls = || Tls {
+        cert: "cert.pem".into(),
+        key: "key.pem".into(),
+    };
+    let with_tls = || Server {
+        port: 443,
+        tls: Some(tls()),
+    };
+    let without_tls = || Server {
+        port: 80,
+        tls: None,
+    };
+
+    // `update` patches the inner value only if there is one.
+    let patch =
+        || ServerPatch::default().tls(|_| OptionPatch::update(TlsPatch::default().cert("new.pem")));
+    assert_eq!(
+        with_tls().with_patch(patch()).tls,
+        Some(Tls {
+            cert: "new.pem".into(),
+            key: "key.pem".into(),
+        })
+    );
+    assert_eq!(without_tls().with_patch(patch()).tls, None);
+
+    // `upsert` initializes a `None` value from `Default` before patching it.
+    let patch =
+        || ServerPatch::default().tls(|_| OptionPatch::upsert(TlsPatch::default().cert("new.pem")));
+    assert_eq!(
+        without_tls().with_patch(patch()).tls,
+        Some(Tls {
+            cert: "new.pem".into(),
+            key: String::new(),
+        })
+    );
+
+    // `Clear` and `Set` replace the whole value.
+    let patch = ServerPatch::default().tls(|_| OptionPatch::Clear);
+    assert_eq!(with_tls().with_patch(patch).tls, None);
+    let patch = ServerPatch::default().tls(|_| Some(tls()).into());
+    assert_eq!(without_tls().with_patch(patch).tls, Some(tls()));
+
+    // Merged patches are applied as if one after the other.
+    let patches = || {
+        [
+            OptionPatch::Keep,
+            OptionPatch::Clear,
+            OptionPatch::Set(tls()),
+            OptionPatch::update(TlsPatch::default().cert("a.pem")),
+            OptionPatch::upsert(TlsPatch::default().key("b.pem")),
+        ]
+    };
+    for (i, j) in (0..5).flat_map(|i| (0..5).map(move |j| (i, j))) {
+        let lhs = || patches()[i].clone();
+        let rhs = || patches()[j].clone();
+        for value in [Some(tls()), None] {
+            assert_eq!(
+                value.clone().with_patch(lhs()).with_patch(rhs()),
+                value.with_patch(lhs().merge(rhs())),
+                "{:?} merged with {:?}",
+                lhs(),
+                rhs(),
+            );
+        }
+    }
+}
+
+#[test]
+fn elementwise() {
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(setters)]
+    struct Shape {
+        #[patch(elementwise)]
+        origin: (f32, f32),
+        #[patch(elementwise)]
+        points: [(f32, f32); 3],
+        #[patch(with = (ColorPatch, Option<u8>))]
+        fill: (Color, u8),
+    }
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(setters)]
+    struct Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+
+    #[derive(Patch)]
+    #[patch(from, setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Pair {
+        #[patch(elementwise)]
+        tuple: (u8, bool),
+        #[patch(elementwise)]
+        array: [u8; 2],
+    }
+
+    assert_eq!(
+        PairPatch::from(Pair {
+            tuple: (1, true),
+            array: [2, 3],
+        }),
+        PairPatch {
+            tuple: (Some(1), Some(true)),
+            array: [Some(2), Some(3)],
+        }
+    );
+
+    let shape = Shape {
+        origin: (0.0, 0.0),
+        points: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        fill: (Color { r: 0, g: 0, b: 0 }, 255),
+    };
+
+    let patch = ShapePatch::default()
+        .origin(|_| (Some(1.0), None))
+        .points(|_| [None, Some((2.0, 0.0)), None])
+        .fill(|(color, _)| (color.g(128), None));
+
+    let patch = patch.merge(ShapePatch::default().origin(|_| (None, Some(2.0))));
+
+    assert_eq!(
+        shape.with_patch(patch),
+        Shape {
+            origin: (1.0, 2.0),
+            points: [(0.0, 0.0), (2.0, 0.0), (0.0, 1.0)],
+            fill: (Color { r: 0, g: 128, b: 0 }, 255),
+        }
+    );
+}
+
+#[test]
+fn conditional() {
+    use patched::{Conditional, Expect, FieldPath, TryPatch};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(expect, setters)]
+    struct Foo {
+        version: u32,
+        #[patch(with = GooPatch)]
+        goo: Box<Goo>,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    let mut value = Foo {
+        version: 1,
+        goo: Box::new(Goo {
+            a: 1,
+            b: "b".into(),
+        }),
+        opt: None,
+        pair: (1, 2),
+    };
+
+    let expected = FooPatch::default()
+        .version(1)
+        .goo(|p| p.a(1))
+        .opt(|_| patched::OptionPatch::Clear)
+        .pair(|_| (None, Some(2)));
+    assert!(value.matches(&expected));
+
+    let patch = FooPatch::default().version(2).goo(|p| p.b("c"));
+    assert_eq!(value.try_patch(Conditional::new(expected, patch)), Ok(()));
+    assert_eq!(value.version, 2);
+    assert_eq!(value.goo.b, "c");
+
+    // Nothing is applied when a field doesn't match.
+    let expected = FooPatch::default()
+        .version(1)
+        .goo(|p| p.a(1).b("b"))
+        .opt(|_| patched::OptionPatch::update(GooPatch::default()))
+        .pair(|_| (Some(2), Some(2)));
+    let conflict = value
+        .try_patch(Conditional::new(expected, FooPatch::default().version(3)))
+        .unwrap_err();
+    assert_eq!(
+        conflict.fields(),
+        [
+            FieldPath::from_iter(["version"]),
+            FieldPath::from_iter(["goo", "b"]),
+            FieldPath::from_iter(["opt"]),
+            FieldPath::from_iter(["pair", "0"]),
+        ]
+    );
+    assert_eq!(
+        conflict.to_string(),
+        "unexpected value of the fields `version`, `goo.b`, `opt`, `pair.0`"
+    );
+    assert_eq!(value.version, 2);
+}
+
+#[test]
+fn conditional_generic() {
+    use patched::Expect;
+
+    #[derive(Patch)]
+    #[patch(expect)]
+    struct Foo<T> {
+        a: T,
+        b: u8,
+    }
+
+    let value = Foo { a: "a", b: 1 };
+    assert!(value.matches(&FooPatch {
+        a: Some("a"),
+        b: None
+    }));
+    assert!(!value.matches(&FooPatch {
+        a: Some("b"),
+        b: Some(1)
+    }));
+}
+
+#[test]
+fn try_merge() {
+    use patched::{FieldPath, TryMerge};
+
+    #[derive(Patch)]
+    #[patch(try_merge, minimize, setters)]
+    #[patch_attr(derive(Debug, Clone))]
+    struct Foo {
+        a: u32,
+        b: String,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    // Fields set by a single patch, or to the same value, don't conflict.
+    let merged = FooPatch::default()
+        .a(1)
+        .goo(|p| p.a(1))
+        .try_merge(FooPatch::default().a(1).b("b").goo(|p| p.b("b")))
+        .unwrap();
+    assert_eq!(merged.a, Some(1));
+    assert_eq!(merged.b.as_deref(), Some("b"));
+    assert_eq!(
+        merged.goo,
+        GooPatch {
+            a: Some(1),
+            b: Some("b".into())
+        }
+    );
+
+    let lhs = FooPatch::default()
+        .a(1)
+        .b("b")
+        .goo(|p| p.a(1).b("b"))
+        .pair(|_| (Some(1), None));
+    let rhs = FooPatch::default()
+        .a(2)
+        .b("b")
+        .goo(|p| p.a(2))
+        .pair(|_| (Some(2), Some(2)));
+    let conflicts = lhs
+        .clone()
+        .try_merge(rhs.clone())
+        .unwrap_err()
+        .into_conflicts();
+
+    let values = conflicts
+        .iter()
+        .map(|conflict| (conflict.path().clone(), conflict.left(), conflict.right()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            (FieldPath::from_iter(["a"]), "1", "2"),
+            (FieldPath::from_iter(["goo", "a"]), "1", "2"),
+            (FieldPath::from_iter(["pair", "0"]), "1", "2"),
+        ]
+    );
+    assert_eq!(conflicts[0].left_value::<u32>(), Some(&1));
+    assert_eq!(conflicts[0].right_value::<u32>(), Some(&2));
+    assert_eq!(conflicts[1].right_value::<u8>(), Some(&2));
+    assert_eq!(conflicts[1].right_value::<u32>(), None);
+
+    // The fields set to their value in the base don't conflict.
+    let base = Foo {
+        a: 1,
+        b: "a".into(),
+        goo: Goo {
+            a: 2,
+            b: String::new(),
+        },
+        pair: (1, 0),
+    };
+    let merged = lhs.clone().try_merge3(rhs.clone(), &base).unwrap();
+    assert_eq!(merged.a, Some(2));
+    assert_eq!(
+        merged.goo,
+        GooPatch {
+            a: Some(1),
+            b: Some("b".into())
+        }
+    );
+    assert_eq!(merged.pair, (Some(2), Some(2)));
+
+    let base = Foo { a: 0, ..base };
+    let conflicts = lhs.try_merge3(rhs, &base).unwrap_err().into_conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path(), &FieldPath::from_iter(["a"]));
+}
+
+#[cfg(feature = "testing")]
+mod laws {
+    use patched::{testing, OptionPatch, Patch};
+
+    use super::{Goo, GooPatch};
+
+    #[derive(Patch, Clone, Debug, PartialEq)]
+    #[cfg_attr(feature = "proptest", patch(arbitrary))]
+    #[patch(from, setters)]
+    #[patch_attr(derive(Clone, Debug))]
+    struct Foo {
+        a: u8,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, bool),
+    }
+
+    fn value() -> Foo {
+        Foo {
+            a: 1,
+            goo: Goo {
+                a: 2,
+                b: "b".into(),
+            },
+            opt: None,
+            pair: (3, false),
+        }
+    }
+
+    #[test]
+    fn laws() {
+        let patches = [
+            FooPatch::default(),
+            FooPatch::default().a(10).goo(|p| p.b("c")),
+            FooPatch::default().opt(|_| OptionPatch::upsert(GooPatch::default().a(20))),
+            FooPatch::default().opt(|_| OptionPatch::Clear),
+            FooPatch::default().pair(|_| (None, Some(true))),
+        ];
+
+        testing::check_laws(&value(), &patches);
+
+        for lhs in &patches {
+            for rhs in &patches {
+                testing::check_merge_from(&value(), lhs.clone(), rhs.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn from() {
+        let other = Foo {
+            a: 0,
+            goo: Goo::default(),
+            opt: Some(Goo::default()),
+            pair: (0, true),
+        };
+
+        testing::check_from::<_, GooPatch>(&value().goo, other.goo.clone());
+        testing::check_from::<_, FooPatch>(&value(), other);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn generated_laws(
+            value in proptest::arbitrary::any::<FooPatch>(),
+            a in proptest::arbitrary::any::<FooPatch>(),
+            b in proptest::arbitrary::any::<FooPatch>(),
+            c in proptest::arbitrary::any::<FooPatch>(),
+        ) {
+            // The values are generated by patching a fixed value.
+            let value = self::value().with_patch(value);
+
+            testing::check_default_identity(&value, a.clone());
+            testing::check_coherence(&value, a.clone(), b.clone());
+            testing::check_merge_from(&value, a.clone(), b.clone());
+            testing::check_associativity(&value, a, b, c);
+        }
+    }
+}
+
+#[test]
+fn patch_log() {
+    use patched::PatchLog;
+
+    #[derive(Patch, Clone, Debug, PartialEq)]
+    #[patch(setters)]
+    #[patch_attr(derive(Clone))]
+    struct Foo {
+        a: u32,
+        b: String,
+    }
+
+    let foo = |a: u32, b: &str| Foo { a, b: b.into() };
+
+    let mut log = PatchLog::new(foo(0, "a"));
+    assert!(log.is_empty());
+    assert_eq!(log.materialize(), foo(0, "a"));
+
+    log.append(FooPatch::default().a(1));
+    log.append(FooPatch::default().b("b"));
+    log.append(FooPatch::default().a(2));
+    log.append(FooPatch::default().a(3).b("c"));
+    log.append(FooPatch::default());
+    assert_eq!(log.end(), 5);
+
+    let states = [
+        foo(0, "a"),
+        foo(1, "a"),
+        foo(1, "b"),
+        foo(2, "b"),
+        foo(3, "c"),
+        foo(3, "c"),
+    ];
+    for (position, state) in states.iter().enumerate() {
+        assert_eq!(log.snapshot_at(position).as_ref(), Some(state));
+    }
+    assert_eq!(log.snapshot_at(6), None);
+
+    // Only the entries entirely inside the range are merged.
+    log.compact(1..4);
+    let ranges = log.entries().map(|(range, _)| range).collect::<Vec<_>>();
+    assert_eq!(ranges, [0..1, 1..4, 4..5]);
+    assert_eq!(log.snapshot_at(2), None);
+    for position in [0, 1, 4, 5] {
+        assert_eq!(log.snapshot_at(position).as_ref(), Some(&states[position]));
+    }
+
+    log.compact(0..5);
+    let ranges = log.entries().map(|(range, _)| range).collect::<Vec<_>>();
+    assert_eq!(ranges, vec![0..5]);
+    assert_eq!(log.materialize(), foo(3, "c"));
+
+    // A checkpoint is only possible at the boundary of an entry.
+    log.append(FooPatch::default().a(4));
+    assert!(!log.checkpoint_at(3));
+    assert!(log.checkpoint_at(5));
+    assert_eq!(log.start(), 5);
+    assert_eq!(log.snapshot(), &foo(3, "c"));
+    assert_eq!(log.snapshot_at(4), None);
+    assert_eq!(log.materialize(), foo(4, "c"));
+}
+
+#[test]
+fn patch_notify() {
+    use std::rc::Rc;
+
+    use patched::{FieldPath, OptionPatch, PatchNotify};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(notify, setters)]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Rc<Goo>,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    let mut value = Foo {
+        a: 1,
+        goo: Rc::new(Goo::default()),
+        opt: None,
+        pair: (1, 2),
+    };
+
+    let patch = FooPatch::default()
+        .a(1)
+        .goo(|p| p.a(0).b("b"))
+        .opt(|_| OptionPatch::upsert(GooPatch::default().a(1)))
+        .pair(|_| (Some(1), Some(3)));
+
+    let mut changes = Vec::new();
+    value.patch_notify(patch, |change| {
+        let path = change.path().to_string();
+        let values = if let (Some(old), Some(new)) =
+            (change.old_value::<String>(), change.new_value::<String>())
+        {
+            format!("{old:?} -> {new:?}")
+        } else if let (Some(old), Some(new)) = (change.old_value::<u8>(), change.new_value::<u8>())
+        {
+            format!("{old} -> {new}")
+        } else if let (Some(old), Some(new)) = (
+            change.old_value::<Option<Goo>>(),
+            change.new_value::<Option<Goo>>(),
+        ) {
+            format!("{old:?} -> {new:?}")
+        } else {
+            panic!("unexpected change of `{path}`");
+        };
+        changes.push(format!("{path}: {values}"));
+    });
+
+    assert_eq!(
+        changes,
+        [
+            r#"goo.b: "" -> "b""#,
+            r#"opt: None -> Some(Goo { a: 1, b: "" })"#,
+            "pair.1: 2 -> 3",
+        ]
+    );
+
+    // Changes inside an existing inner value are reported for each field.
+    let patch = FooPatch::default().opt(|_| OptionPatch::update(GooPatch::default().b("c")));
+    assert_eq!(
+        value.patch_changes(patch),
+        [FieldPath::from_iter(["opt", "b"])]
+    );
+    assert_eq!(value.opt.as_ref().unwrap().b, "c");
+
+    let patch = FooPatch::default().opt(|_| OptionPatch::Clear);
+    assert_eq!(value.patch_changes(patch), [FieldPath::from_iter(["opt"])]);
+    assert_eq!(
+        value.patch_changes(FooPatch::default().opt(|_| OptionPatch::Clear)),
+        []
+    );
+
+    // A shared value isn't cloned when its nested patch is empty.
+    let shared = Rc::clone(&value.goo);
+    assert_eq!(
+        value.patch_changes(FooPatch::default().a(2)),
+        [FieldPath::from_iter(["a"])]
+    );
+    assert!(Rc::ptr_eq(&value.goo, &shared));
+}
+
+#[test]
+fn patch_ref() {
+    use std::sync::Arc;
+
+    use patched::{OptionPatch, PatchRef};
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(by_ref, setters)]
+    struct Foo {
+        a: u32,
+        b: String,
+        #[patch(with = GooPatch)]
+        goo: Arc<Goo>,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    let value = Foo {
+        a: 1,
+        b: "b".into(),
+        goo: Arc::new(Goo::default()),
+        opt: None,
+        pair: (1, 2),
+    };
+
+    let patch = FooPatch::default()
+        .b("c")
+        .goo(|p| p.a(1))
+        .opt(|_| OptionPatch::upsert(GooPatch::default().b("d")))
+        .pair(|_| (None, Some(3)));
+
+    // The same patch is applied to many values without being consumed.
+    let values = vec![value.clone(); 3]
+        .into_iter()
+        .map(|value| value.with_patch_ref(&patch))
+        .collect::<Vec<_>>();
+
+    let expected = value.with_patch(patch);
+    assert_eq!(
+        expected,
+        Foo {
+            a: 1,
+            b: "c".into(),
+            goo: Arc::new(Goo {
+                a: 1,
+                b: String::new()
+            }),
+            opt: Some(Goo {
+                a: 0,
+                b: "d".into()
+            }),
+            pair: (1, 3),
+        }
+    );
+    assert!(values.iter().all(|value| *value == expected));
+}
+
+#[test]
+fn merge_from() {
+    use patched::MergeFrom;
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(setters)]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+    }
+
+    let mut patch = FooPatch::default().a(1).goo(|p| p.a(1));
+    patch.merge_from(FooPatch::default().goo(|p| p.b("b")));
+    assert_eq!(patch.a, Some(1));
+    assert_eq!(
+        patch.goo,
+        GooPatch {
+            a: Some(1),
+            b: Some("b".into())
+        }
+    );
+
+    let patch =
+        FooPatch::merge_all((0..1000u32).map(|i| FooPatch::default().a(i).goo(|p| p.a(i as u8))));
+    assert_eq!(patch.a, Some(999));
+    assert_eq!(
+        patch.goo,
+        GooPatch {
+            a: Some(231),
+            b: None
+        }
+    );
+}
+
+#[test]
+fn try_from_patch() {
+    use patched::{FieldPath, FromPatch, OptionPatch};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(try_into, setters)]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Box<Goo>,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+        #[patch(default)]
+        b: String,
+        #[patch(default = 10)]
+        c: u8,
+        #[patch(with = GooPatch, default = Goo { a: 1, b: "b".into() })]
+        d: Goo,
+    }
+
+    let patch = FooPatch::default()
+        .a(1)
+        .goo(|p| p.a(2).b("goo"))
+        .opt(|_| OptionPatch::update(GooPatch::default().a(3).b("opt")))
+        .pair(|_| (Some(4), Some(5)))
+        .d(|p| p.b("d"));
+
+    assert_eq!(
+        Foo::try_from(patch),
+        Ok(Foo {
+            a: 1,
+            goo: Box::new(Goo {
+                a: 2,
+                b: "goo".into()
+            }),
+            opt: Some(Goo {
+                a: 3,
+                b: "opt".into()
+            }),
+            pair: (4, 5),
+            b: String::new(),
+            c: 10,
+            d: Goo {
+                a: 1,
+                b: "d".into()
+            },
+        })
+    );
+
+    let patch = FooPatch::default()
+        .goo(|p| p.b("goo"))
+        .opt(|_| OptionPatch::Clear)
+        .pair(|_| (None, Some(5)));
+    let error = Foo::try_from_patch(patch).unwrap_err();
+    assert_eq!(
+        error.fields(),
+        [
+            FieldPath::from_iter(["a"]),
+            FieldPath::from_iter(["goo", "a"]),
+            FieldPath::from_iter(["pair", "0"]),
+        ]
+    );
+    assert_eq!(error.to_string(), "missing fields `a`, `goo.a`, `pair.0`");
+}
+
+#[test]
+fn try_from_patch_default() {
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(try_into, try_into_default, setters)]
+    struct Foo {
+        a: u32,
+        b: String,
+    }
+
+    impl Default for Foo {
+        fn default() -> Self {
+            Self {
+                a: 1,
+                b: "b".into(),
+            }
+        }
+    }
+
+    assert_eq!(
+        Foo::try_from(FooPatch::default().b("c")),
+        Ok(Foo {
+            a: 1,
+            b: "c".into()
+        })
+    );
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(try_into)]
+    struct Unit;
+
+    assert_eq!(Unit::try_from(UnitPatch), Ok(Unit));
+}
+
+#[test]
+fn minimize() {
+    use std::rc::Rc;
+
+    // The inherent methods of the patch struct don't require importing `Minimize`.
+    use patched::OptionPatch;
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(minimize, setters)]
+    #[patch_attr(derive(Debug, PartialEq, Clone))]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Rc<Goo>,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    let value = Foo {
+        a: 1,
+        goo: Rc::new(Goo::default()),
+        opt: Some(Goo::default()),
+        pair: (1, 2),
+    };
+
+    let patch = FooPatch::default()
+        .a(1)
+        .goo(|p| p.a(0).b("b"))
+        .opt(|_| OptionPatch::upsert(GooPatch::default().a(0)))
+        .pair(|_| (Some(1), Some(3)));
+
+    assert!(!patch.is_noop_for(&value));
+    assert_eq!(
+        patch.minimize(&value),
+        FooPatch::default()
+            .goo(|p| p.b("b"))
+            .pair(|_| (None, Some(3)))
+    );
+
+    // `OptionPatch` variants that leave the value as it is become `Keep`.
+    let patch = FooPatch::default().opt(|_| OptionPatch::Set(Goo::default()));
+    assert!(patch.is_noop_for(&value));
+    assert_eq!(patch.minimize(&value), FooPatch::default());
+
+    let value = Foo { opt: None, ..value };
+    let patch = FooPatch::default().opt(|_| OptionPatch::Clear);
+    assert_eq!(patch.minimize(&value), FooPatch::default());
+    let patch = FooPatch::default().opt(|_| OptionPatch::update(GooPatch::default().a(1)));
+    assert_eq!(patch.minimize(&value), FooPatch::default());
+    let patch = FooPatch::default().opt(|_| OptionPatch::upsert(GooPatch::default().a(0)));
+    assert_eq!(patch.clone().minimize(&value), patch);
+
+    // A patch can be minimized against a value behind a smart pointer.
+    let patch = GooPatch::default().a(0).b("b");
+    assert_eq!(
+        patch.minimize(&Box::new(Goo::default())),
+        GooPatch::default().b("b")
+    );
+}
+
+#[test]
+fn patch_algebra() {
+    use patched::{FieldPath, Intersect, OptionPatch, Restrict, Subtract};
+
+    #[derive(Patch)]
+    #[patch(setters, algebra)]
+    #[patch_attr(derive(Debug, PartialEq, Clone))]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+    }
+
+    let patch = FooPatch::default()
+        .a(1)
+        .goo(|p| p.a(2).b("b"))
+        .opt(|_| OptionPatch::update(GooPatch::default().a(3).b("c")));
+    let other = FooPatch::default()
+        .goo(|p| p.a(4))
+        .opt(|_| OptionPatch::update(GooPatch::default().b("d")));
+
+    assert_eq!(
+        patch.clone().subtract(&other),
+        FooPatch::default()
+            .a(1)
+            .goo(|p| p.b("b"))
+            .opt(|_| OptionPatch::update(GooPatch::default().a(3)))
+    );
+    assert_eq!(
+        patch.clone().intersect(&other),
+        FooPatch::default()
+            .goo(|p| p.a(2))
+            .opt(|_| OptionPatch::update(GooPatch::default().b("c")))
+    );
+
+    // A replaced value is removed by any patch of the same field.
+    let set = FooPatch::default().opt(|_| OptionPatch::Set(Goo::default()));
+    assert_eq!(set.clone().subtract(&other), FooPatch::default());
+    assert_eq!(set.clone().intersect(&other), set);
+
+    let allowed = [
+        FieldPath::from_iter(["goo"]),
+        FieldPath::from_iter(["opt", "b"]),
+    ];
+    assert_eq!(
+        patch.restrict(&allowed),
+        FooPatch::default()
+            .goo(|p| p.a(2).b("b"))
+            .opt(|_| OptionPatch::update(GooPatch::default().b("c")))
+    );
+    assert_eq!(set.restrict(&allowed), FooPatch::default());
+}
+
+#[test]
+fn mask() {
+    use patched::{Mask, Masked, MaskedFrom, OptionPatch};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(mask, setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(option_with = GooPatch)]
+        opt: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, u8),
+    }
+
+    let patch = FooPatch::default()
+        .a(1)
+        .goo(|p| p.b("b"))
+        .pair(|_| (None, Some(3)));
+
+    let mut mask = FooMask::A;
+    mask.goo = GooMask::B;
+    mask.pair = (false, true);
+    assert_eq!(patch.mask(), mask);
+    assert!(FooPatch::default().mask().is_empty());
+    assert_eq!(
+        format!("{:?}", mask),
+        "FooMask { a: true, goo: GooMask { a: false, b: true }, opt: false, pair: (false, true) }"
+    );
+
+    let other = FooMask::GOO | FooMask::OPT;
+    let mut union = FooMask::A | other;
+    union.pair = (false, true);
+    assert_eq!(mask | other, union);
+    let mut intersection = FooMask::EMPTY;
+    intersection.goo = GooMask::B;
+    assert_eq!(mask & other, intersection);
+    let mut difference = FooMask::A;
+    difference.pair = (false, true);
+    assert_eq!(mask - other, difference);
+    assert!((mask | other).contains(mask));
+    assert!(!mask.contains(other));
+
+    let mut all = FooMask::EMPTY;
+    all.insert(FooMask::A | FooMask::GOO | FooMask::OPT | FooMask::PAIR);
+    assert_eq!(all, FooMask::ALL);
+    all.remove(FooMask::ALL - FooMask::A);
+    assert_eq!(all, FooMask::A);
+
+    assert_eq!(patch.retain(other), FooPatch::default().goo(|p| p.b("b")));
+
+    let value = Foo {
+        a: 1,
+        goo: Goo {
+            a: 2,
+            b: "b".into(),
+        },
+        opt: None,
+        pair: (3, 4),
+    };
+    assert_eq!(
+        FooPatch::from_value_masked(&value, other),
+        FooPatch::default()
+            .goo(|p| p.a(2).b("b"))
+            .opt(|_| OptionPatch::Clear)
+    );
+    assert_eq!(
+        Foo {
+            a: 0,
+            goo: Goo::default(),
+            opt: Some(Goo::default()),
+            pair: (0, 0),
+        }
+        .with_patch(FooPatch::from_value_masked(&value, FooMask::all())),
+        value
+    );
+}
+
+#[test]
+fn mask_paths() {
+    use patched::{FieldPath, Mask, Masked, MaskedFrom};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(mask, setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Config {
+        name: String,
+        #[patch(with = ServerPatch)]
+        server: Server,
+        #[patch(elementwise)]
+        ports: [u16; 2],
+    }
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(mask, setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Server {
+        r#type: String,
+        #[patch(with = TlsPatch)]
+        tls: Tls,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(mask, setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Tls {
+        cert: String,
+        key: String,
+    }
+
+    let value = Config {
+        name: "a".into(),
+        server: Server {
+            r#type: "b".into(),
+            tls: Tls {
+                cert: "c".into(),
+                key: "d".into(),
+            },
+        },
+        ports: [1, 2],
+    };
+
+    let patch =
+        ConfigPatch::from_value_paths(&value, ["server.tls.cert", "server.type", "ports.1"])
+            .unwrap();
+    assert_eq!(
+        patch,
+        ConfigPatch::default()
+            .server(|p| p.r#type("b").tls(|p| p.cert("c")))
+            .ports(|_| [None, Some(2)])
+    );
+
+    let paths = |paths: &[&str]| -> Vec<FieldPath> {
+        paths
+            .iter()
+            .map(|path| FieldPath::from_iter(path.split('.').map(String::from)))
+            .collect()
+    };
+    assert_eq!(
+        patch.paths(),
+        paths(&["server.type", "server.tls.cert", "ports.1"])
+    );
+
+    // A path to a nested patch includes all its fields.
+    let patch = ConfigPatch::from_value_paths(&value, ["server.tls"]).unwrap();
+    assert_eq!(patch.paths(), paths(&["server.tls.cert", "server.tls.key"]));
+    assert_eq!(ConfigMask::from_paths(["ports"]).unwrap().ports, [true; 2]);
+
+    for path in [
+        "",
+        "server.tls.cert.len",
+        "ports.2",
+        "server.port",
+        "name.0",
+    ] {
+        let error = ConfigPatch::from_value_paths(&value, ["name", path]).unwrap_err();
+        assert_eq!(error.path(), path);
+    }
+}
+
+#[test]
+fn mask_generic() {
+    use patched::{Mask, Masked, MaskedFrom};
+
+    #[derive(Patch, Debug, PartialEq)]
+    #[patch(mask)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Foo<T>(T, #[patch(with = GooPatch)] Goo);
+
+    let value = Foo(
+        1u32,
+        Goo {
+            a: 2,
+            b: "b".into(),
+        },
+    );
+    let mut mask = FooMask::_0;
+    mask._1 = GooMask::A;
+
+    let patch = FooPatch::from_value_masked(&value, mask);
+    assert_eq!(
+        patch,
+        FooPatch(
+            Some(1),
+            GooPatch {
+                a: Some(2),
+                ..GooPatch::default()
+            }
+        )
+    );
+    assert_eq!(patch.mask(), mask);
+    assert_eq!(FooMask::from_paths(["0", "1.a"]).unwrap(), mask);
+    assert_eq!(
+        format!("{:?}", mask),
+        "FooMask { 0: true, 1: GooMask { a: true, b: false } }"
+    );
+}
+
+#[test]
+fn compact() {
+    use std::{mem::size_of, rc::Rc};
+
+    use patched::{FieldPath, Intersect, Merge, MergeFrom, Restrict, Subtract};
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(compact, setters, algebra)]
+    struct Foo {
+        a: u32,
+        b: u32,
+        r#type: String,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[cfg(any())]
+        disabled: u8,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(setters, algebra)]
+    #[patch_attr(derive(Debug, PartialEq, Clone))]
+    struct Goo {
+        a: u8,
+    }
+
+    #[derive(Patch)]
+    #[patch(setters, accessors)]
+    struct Plain {
+        a: u32,
+        b: u32,
+        r#type: String,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+    }
+
+    assert!(size_of::<FooPatch>() < size_of::<PlainPatch>());
+
+    let mut value = Foo {
+        a: 1,
+        b: 2,
+        r#type: "a".into(),
+        goo: Goo { a: 3 },
+    };
+
+    let patch = FooPatch::default().a(4).goo(|p| p.a(5));
+    assert_eq!(patch.get_a(), Some(&4));
+    assert_eq!(patch.get_b(), None);
+    assert_eq!(
+        format!("{patch:?}"),
+        "FooPatch { a: Some(4), b: None, type: None, goo: GooPatch { a: Some(5) } }"
+    );
+
+    value.patch(patch.clone());
+    assert_eq!(
+        value,
+        Foo {
+            a: 4,
+            b: 2,
+            r#type: "a".into(),
+            goo: Goo { a: 5 },
+        }
+    );
+
+    let mut other = FooPatch::default().r#type("b");
+    other.set_a(Some(6));
+    let merged = patch.clone().merge(other.clone());
+    assert_eq!(merged.get_a(), Some(&6));
+    assert_eq!(merged.get_type().map(String::as_str), Some("b"));
+    assert_eq!(merged.goo, GooPatch::default().a(5));
+
+    let mut merged_from = patch;
+    merged_from.merge_from(other);
+    assert_eq!(merged_from, merged);
+
+    let mut patch = merged;
+    assert_eq!(patch.take_type(), Some("b".to_owned()));
+    assert_eq!(patch.take_type(), None);
+    patch.set_a(None);
+    assert_eq!(patch, FooPatch::default().goo(|p| p.a(5)));
+
+    // The default layout has the same accessors with `accessors`.
+    let mut plain = PlainPatch::default().a(4);
+    assert_eq!(plain.get_a(), Some(&4));
+    plain.set_type(Some("b".into()));
+    assert_eq!(plain.take_type(), Some("b".to_owned()));
+    assert_eq!(plain.get_type(), None);
+
+    let patch = FooPatch::default().a(1).b(2).goo(|p| p.a(3));
+    let other = FooPatch::default().b(4).goo(|p| p.a(5));
+    assert_eq!(patch.clone().subtract(&other), FooPatch::default().a(1));
+    assert_eq!(
+        patch.clone().intersect(&other),
+        FooPatch::default().b(2).goo(|p| p.a(3))
+    );
+    assert_eq!(
+        patch.restrict(&[FieldPath::from_iter(["a"]), FieldPath::from_iter(["goo"])]),
+        FooPatch::default().a(1).goo(|p| p.a(3))
+    );
+
+    // The values are dropped with the patch.
+    let counter = Rc::new(());
+    #[derive(Patch)]
+    #[patch(compact, setters)]
+    struct Counted {
+        a: Rc<()>,
+        b: Rc<()>,
+    }
+    let mut patch = CountedPatch::default()
+        .a(counter.clone())
+        .b(counter.clone());
+    patch.set_a(Some(counter.clone()));
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop(patch.clone());
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop(patch);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[cfg(feature = "wire")]
+#[test]
+fn wire_values() {
+    use std::fmt::Debug;
+
+    use patched::wire::{self, Wire};
+
+    fn round_trip<T: Wire + PartialEq + Debug>(value: T, len: usize) {
+        let bytes = wire::encode(&value);
+        assert_eq!(bytes.len(), len, "{value:?}");
+        assert_eq!(wire::decode::<T>(&bytes), Ok(value));
+    }
+
+    round_trip(0u32, 1);
+    round_trip(127u32, 1);
+    round_trip(128u32, 2);
+    round_trip(u64::MAX, 10);
+    round_trip(u128::MAX, 19);
+    round_trip(-1i32, 1);
+    round_trip(i64::MIN, 10);
+    round_trip(-3i8, 1);
+    round_trip(1.5f64, 8);
+    round_trip('é', 2);
+    round_trip(String::from("abc"), 4);
+    round_trip(vec![Some(1u16), None], 4);
+    round_trip(([true, false], (), Box::new(2u8)), 3);
+
+    use patched::wire::DecodeError;
+    assert_eq!(wire::decode::<u8>(&[]), Err(DecodeError::UnexpectedEnd));
+    assert_eq!(wire::decode::<u8>(&[1, 2]), Err(DecodeError::TrailingBytes));
+    assert_eq!(
+        wire::decode::<bool>(&[2]),
+        Err(DecodeError::InvalidValue("bool"))
+    );
+    assert_eq!(
+        wire::decode::<u16>(&[0xff, 0xff, 0x04]),
+        Err(DecodeError::InvalidValue("u16"))
+    );
+    assert_eq!(
+        wire::decode::<u32>(&[0x80]),
+        Err(DecodeError::UnexpectedEnd)
+    );
+    assert_eq!(
+        wire::decode::<String>(&[1, 0xff]),
+        Err(DecodeError::InvalidValue("String"))
+    );
+
+    // The length of a `Vec` of zero-sized values is limited.
+    round_trip(vec![(); 1 << 16], 3);
+    assert_eq!(
+        wire::decode::<Vec<()>>(&wire::encode(&usize::MAX)),
+        Err(DecodeError::InvalidValue("Vec"))
+    );
+}
+
+#[cfg(feature = "wire")]
+#[test]
+fn wire_patch() {
+    use patched::wire::{self, WireField};
+
+    #[derive(Patch)]
+    #[patch(setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Foo {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(elementwise)]
+        pair: (u8, String),
+        c: i64,
+    }
+
+    #[derive(Patch)]
+    #[patch(setters)]
+    #[patch_attr(derive(Debug, PartialEq))]
+    struct Goo {
+        a: u8,
+    }
+
+    let patch = FooPatch::default().a(300).goo(|p| p.a(1)).c(-1i64);
+    let bytes = wire::encode(&patch);
+    assert_eq!(bytes, [4, 0b1011, 7, 0xac, 2, 1, 1, 1, 1, 1]);
+    assert_eq!(wire::decode::<FooPatch>(&bytes), Ok(patch));
+
+    let patch = FooPatch::default().pair(|_| (None, Some("b".into())));
+    assert_eq!(wire::decode(&wire::encode(&patch)), Ok(patch));
+    assert!(!FooPatch::default().is_set());
+
+    // A newer version with an appended field, and an older version without the last field.
+    let newer = (Some(1u8), None::<u8>, Some(3u8));
+    let mut bytes = Vec::new();
+    newer.encode_set(&mut bytes);
+    assert_eq!(
+        <(Option<u8>, Option<u8>)>::decode_set(&mut &bytes[..]),
+        Ok((Some(1), None))
+    );
+    assert_eq!(
+        <(Option<u8>, Option<u8>, Option<u8>, Option<u8>)>::decode_set(&mut &bytes[..]),
+        Ok((Some(1), None, Some(3), None))
+    );
+
+    // The values after the known fields are skipped only if they belong to appended fields.
+    use patched::wire::DecodeError;
+    assert_eq!(
+        <(Option<u8>, Option<u8>)>::decode_set(&mut &[2, 0b01, 2, 1, 9][..]),
+        Err(DecodeError::TrailingBytes)
+    );
+    assert_eq!(
+        <(Option<u8>, Option<u8>)>::decode_set(&mut &[3, 0b101, 2, 1, 9][..]),
+        Ok((Some(1), None))
+    );
+    assert_eq!(
+        wire::decode::<GooPatch>(&[1, 0b1, 2, 1, 9]),
+        Err(DecodeError::TrailingBytes)
+    );
+
+    #[derive(Patch)]
+    #[patch(compact, setters)]
+    struct Compact {
+        a: u32,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+    }
+
+    let patch = CompactPatch::default().a(7);
+    assert_eq!(wire::encode(&patch), [2, 0b1, 1, 7]);
+    let decoded = wire::decode::<CompactPatch>(&wire::encode(&patch)).unwrap();
+    assert_eq!(decoded, patch);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn deserialize_patch() {
+    use std::fmt::Debug;
+
+    use patched::serde::{patch_from, DeserializePatch};
+    use serde::Deserialize;
+
+    /// Patches `value` directly from `json`, and checks that it's like deserializing the patch
+    /// struct and applying it.
+    fn patch_json<T, P>(value: &mut T, json: &str) -> Result<(), serde_json::Error>
+    where
+        T: for<'de> DeserializePatch<'de, Patch = P> + Patch<P> + Clone + PartialEq + Debug,
+        P: for<'de> Deserialize<'de>,
+    {
+        let expected = serde_json::from_str::<P>(json).map(|patch| value.clone().with_patch(patch));
+        let result = patch_from(value, &mut serde_json::Deserializer::from_str(json));
+        match (&result, expected) {
+            (Ok(()), Ok(expected)) => assert_eq!(*value, expected),
+            (Err(_), Err(_)) => {}
+            (result, expected) => panic!("{:?} != {:?}", result, expected),
+        }
+        result
+    }
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(deserialize_patch)]
+    #[patch_attr(derive(Deserialize))]
+    #[patch_attr(serde(default, rename_all = "camelCase"))]
+    struct Foo<T> {
+        a: u32,
+        r#type: Option<String>,
+        #[patch(with = GooPatch)]
+        goo: Goo,
+        #[patch(with = GooPatch)]
+        boxed_goo: Box<Goo>,
+        #[patch(option_with = GooPatch)]
+        maybe: Option<Goo>,
+        #[patch(elementwise)]
+        pair: (u8, String),
+        #[patch_attr(serde(rename = "gen", alias = "generic"))]
+        generic: T,
+        #[patch_attr(serde(skip))]
+        skipped: u8,
+    }
+
+    #[derive(Patch, Debug, PartialEq, Clone, Default, Deserialize)]
+    #[patch(deserialize_patch)]
+    #[patch_attr(derive(Deserialize))]
+    #[patch_attr(derive(Clone))]
+    #[patch_attr(serde(default, deny_unknown_fields))]
+    struct Goo {
+        a: u8,
+        b: u8,
+    }
+
+    let mut value = Foo {
+        a: 1,
+        r#type: Some("a".into()),
+        goo: Goo { a: 2, b: 3 },
+        boxed_goo: Box::new(Goo { a: 4, b: 5 }),
+        maybe: None,
+        pair: (6, "b".into()),
+        generic: 7u64,
+        skipped: 8,
+    };
+
+    // A `null` leaves a field untouched, like a `None` field of the patch struct.
+    patch_json(
+        &mut value,
+        r#"{
+            "a": 10,
+            "type": null,
+            "goo": { "b": 30 },
+            "boxedGoo": { "a": 40 },
+            "maybe": { "Set": { "a": 50, "b": 51 } },
+            "pair": [null, "c"],
+            "unknown": { "a": 1 }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        Foo {
+            a: 10,
+            r#type: Some("a".into()),
+            goo: Goo { a: 2, b: 30 },
+            boxed_goo: Box::new(Goo { a: 40, b: 5 }),
+            maybe: Some(Goo { a: 50, b: 51 }),
+            pair: (6, "c".into()),
+            generic: 7,
+            skipped: 8,
+        }
+    );
+
+    // `option_with` follows `OptionPatch`.
+    patch_json(
+        &mut value,
+        r#"{ "maybe": { "Patch": { "patch": { "b": 60 }, "or_insert": null } }, "gen": 70 }"#,
+    )
+    .unwrap();
+    assert_eq!(value.maybe, Some(Goo { a: 50, b: 60 }));
+    assert_eq!(value.generic, 70);
+    patch_json(&mut value, r#"{ "maybe": "Clear", "generic": 71 }"#).unwrap();
+    assert_eq!(value.maybe, None);
+    assert_eq!(value.generic, 71);
+    patch_json(&mut value, r#"{ "maybe": null }"#).unwrap_err();
+
+    // The renamed and skipped fields are unknown keys.
+    patch_json(&mut value, r#"{ "boxed_goo": { "a": 1 }, "skipped": 1 }"#).unwrap();
+    assert_eq!(value.boxed_goo, Box::new(Goo { a: 40, b: 5 }));
+    assert_eq!(value.skipped, 8);
+
+    // `deny_unknown_fields` of a nested patch.
+    patch_json(&mut value, r#"{ "goo": { "c": 1 } }"#).unwrap_err();
+
+    // The fields before the error are written.
+    let error = patch_json(&mut value, r#"{ "a": 11, "goo": { "a": "x" } }"#).unwrap_err();
+    assert!(error.is_data());
+    assert_eq!(value.a, 11);
+
+    // A sequence lists the fields in declaration order, like the formats which aren't
+    // self-describing, and can end before the fields with a default.
+    patch_json(
+        &mut value,
+        r#"[12, "t", [null, 31], { "b": 41 }, { "Set": { "a": 1, "b": 2 } }, [7, null], 72]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        Foo {
+            a: 12,
+            r#type: Some("t".into()),
+            goo: Goo { a: 2, b: 31 },
+            boxed_goo: Box::new(Goo { a: 40, b: 41 }),
+            maybe: Some(Goo { a: 1, b: 2 }),
+            pair: (7, "c".into()),
+            generic: 72,
+            skipped: 8,
+        }
+    );
+    patch_json(&mut value, r#"[13, null]"#).unwrap();
+    assert_eq!(value.a, 13);
+    assert_eq!(value.r#type.as_deref(), Some("t"));
+
+    #[derive(Patch, Debug, PartialEq, Clone)]
+    #[patch(deserialize_patch)]
+    #[patch_attr(derive(Deserialize))]
+    struct Bar {
+        a: u8,
+        #[patch_attr(serde(default))]
+        b: u8,
+        c: u8,
+    }
+
+    let mut value = Bar { a: 1, b: 2, c: 3 };
+    patch_json(&mut value, r#"[10, null, 30]"#).unwrap();
+    assert_eq!(value, Bar { a: 10, b: 2, c: 30 });
+    patch_json(&mut value, r#"[11, 21]"#).unwrap_err();
+}
+
+/// `DeserializePatch` isn't implemented for a remote type, which can still be patched.
+#[cfg(feature = "serde")]
+#[test]
+fn deserialize_patch_remote() {
+    #[derive(Patch)]
+    #[patch(remote = std::ops::Range<u32>)]
+    #[patch_attr(derive(serde::Deserialize))]
+    #[patch_attr(serde(default))]
+    #[allow(dead_code)]
+    struct RangeDef {
+        start: u32,
+        end: u32,
+    }
+
+    let patch: RangePatch = serde_json::from_str(r#"{ "end": 10 }"#).unwrap();
+    let mut range = 1..2;
+    range.patch(patch);
+    assert_eq!(range, 1..10);
+}
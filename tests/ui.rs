@@ -2,4 +2,7 @@
 fn ui() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/ui/pass/*.rs");
+    #[cfg(feature = "serde")]
+    t.compile_fail("tests/ui/serde/*.rs");
 }
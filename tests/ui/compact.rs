@@ -0,0 +1,13 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(compact)]
+struct Foo(u8);
+
+#[derive(Patch)]
+#[patch(compact, from, mask)]
+struct Bar {
+    a: u8,
+}
+
+fn main() {}
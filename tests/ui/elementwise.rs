@@ -0,0 +1,11 @@
+use patched::Patch;
+
+#[derive(Patch)]
+struct Foo {
+    #[patch(elementwise)]
+    a: Vec<u8>,
+    #[patch(elementwise, with = (Option<u8>, Option<u8>))]
+    b: (u8, u8),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(field_vis = public)]
+struct Foo {
+    a: u32,
+    #[patch(vis = "pub(")]
+    b: String,
+}
+
+fn main() {}
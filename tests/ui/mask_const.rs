@@ -0,0 +1,17 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(mask)]
+struct Foo {
+    all: u8,
+}
+
+#[derive(Patch)]
+#[patch(mask)]
+#[allow(non_snake_case)]
+struct Bar {
+    a: u8,
+    A: u8,
+}
+
+fn main() {}
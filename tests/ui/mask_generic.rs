@@ -0,0 +1,17 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(mask)]
+struct Foo<T> {
+    a: T,
+    #[patch(with = BarPatch<T>)]
+    bar: Bar<T>,
+}
+
+#[derive(Patch)]
+#[patch(mask)]
+struct Bar<T> {
+    b: T,
+}
+
+fn main() {}
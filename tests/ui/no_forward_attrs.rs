@@ -0,0 +1,15 @@
+#![deny(missing_docs)]
+
+//! The doc comments are not forwarded to the patch struct.
+
+use patched::Patch;
+
+/// A foo.
+#[derive(Patch)]
+#[patch(no_forward_attrs)]
+pub struct Foo {
+    /// The `a` field.
+    pub a: u64,
+}
+
+fn main() {}
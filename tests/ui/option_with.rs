@@ -0,0 +1,14 @@
+use patched::Patch;
+
+#[derive(Patch)]
+struct Foo {
+    #[patch(option_with = GooPatch)]
+    a: Goo,
+}
+
+#[derive(Patch, Default)]
+struct Goo {
+    a: u8,
+}
+
+fn main() {}
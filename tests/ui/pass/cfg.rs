@@ -0,0 +1,49 @@
+use patched::Patch;
+
+/// A type implementing none of the traits required by the flags below.
+struct NotPatchable;
+
+#[derive(Patch)]
+#[patch(from, try_merge, minimize)]
+struct Foo {
+    a: u64,
+    #[cfg(feature = "schemars")]
+    b: bool,
+    #[cfg(not(feature = "schemars"))]
+    c: String,
+    #[cfg(any())]
+    d: NotPatchable,
+}
+
+#[derive(Patch)]
+#[patch(from)]
+struct Goo(
+    u64,
+    #[cfg(feature = "schemars")] bool,
+    #[cfg(not(feature = "schemars"))] String,
+    #[cfg(any())] NotPatchable,
+);
+
+fn main() {
+    let mut foo = Foo {
+        a: 0,
+        #[cfg(feature = "schemars")]
+        b: false,
+        #[cfg(not(feature = "schemars"))]
+        c: String::new(),
+    };
+
+    foo.patch(FooPatch {
+        a: Some(1),
+        ..Default::default()
+    });
+
+    let _ = FooPatch::from(foo);
+
+    #[cfg(feature = "schemars")]
+    let goo = Goo(0, false);
+    #[cfg(not(feature = "schemars"))]
+    let goo = Goo(0, String::new());
+
+    let _ = GooPatch::from(goo);
+}
@@ -0,0 +1,30 @@
+//! The doc comments are forwarded to the patch struct.
+
+#![deny(missing_docs)]
+
+use patched::Patch;
+
+/// A foo.
+#[derive(Patch)]
+pub struct Foo {
+    /// The `a` field.
+    pub a: u64,
+    /// The `b` field.
+    #[deprecated]
+    pub b: bool,
+}
+
+#[derive(Patch)]
+#[patch(rewrite_docs)]
+#[allow(missing_docs)]
+pub struct Goo {
+    pub a: u64,
+}
+
+fn main() {
+    #[allow(deprecated)]
+    let _ = FooPatch {
+        a: Some(1),
+        b: None,
+    };
+}
@@ -0,0 +1,16 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch_attr(derive(Debug, Default))]
+struct Foo {
+    a: u8,
+}
+
+#[derive(Patch)]
+#[patch(compact)]
+#[patch_attr(derive(Clone, std::fmt::Debug))]
+struct Bar {
+    a: u8,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use patched::{patch, Patch};
+
+#[derive(Patch)]
+struct Foo {
+    a: u32,
+    #[patch(with = GooPatch)]
+    b: Goo,
+}
+
+#[derive(Patch)]
+struct Goo {
+    c: bool,
+}
+
+fn main() {
+    let _ = patch!(Foo { a: 1, x: 2 });
+    let _ = patch!(Foo { b.y: true });
+    let _ = patch!(Foo { a: 1, a: 2 });
+    let _ = patch!(Foo { a: null });
+    let _ = patch!(Foo { b: GooPatch::default() });
+}
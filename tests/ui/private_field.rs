@@ -0,0 +1,20 @@
+mod inner {
+    use patched::Patch;
+
+    #[derive(Patch)]
+    #[patch(vis = "pub(crate)", field_vis = "pub(super)")]
+    pub struct Foo {
+        pub a: u32,
+        #[patch(vis = inherit)]
+        b: String,
+    }
+}
+
+fn main() {
+    let mut patch = inner::FooPatch::default();
+    patch.a = Some(1);
+    patch.b = Some(String::from("Hello"));
+
+    // The fields which aren't `pub` get a setter without `#[patch(setters)]`.
+    let _ = inner::FooPatch::default().a(1).b("Hello");
+}
@@ -0,0 +1,17 @@
+use patched::Patch;
+
+mod other {
+    pub struct Config {
+        pub a: u32,
+        pub b: String,
+    }
+}
+
+#[derive(Patch)]
+#[patch(remote = other::Config)]
+struct ConfigDef {
+    a: u64,
+    c: String,
+}
+
+fn main() {}
@@ -0,0 +1,34 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(deserialize_patch)]
+#[patch_attr(serde(tag = "type"))]
+struct Foo {
+    #[patch_attr(serde(flatten))]
+    a: u8,
+}
+
+#[derive(Patch)]
+#[patch(deserialize_patch)]
+#[patch_attr(serde(rename_all = "Title Case"))]
+struct Bar {
+    a: u8,
+}
+
+#[derive(Patch)]
+#[patch(deserialize_patch)]
+struct Baz(u8);
+
+mod other {
+    pub struct Qux {
+        pub a: u8,
+    }
+}
+
+#[derive(Patch)]
+#[patch(remote = other::Qux, deserialize_patch)]
+struct QuxDef {
+    a: u8,
+}
+
+fn main() {}
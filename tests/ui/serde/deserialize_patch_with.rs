@@ -0,0 +1,31 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(deserialize_patch)]
+struct Foo {
+    #[patch(with = CustomPatch)]
+    a: Goo,
+}
+
+#[derive(Patch, Default)]
+#[patch(deserialize_patch)]
+struct Goo {
+    a: u8,
+}
+
+#[derive(Default)]
+struct CustomPatch;
+
+impl patched::Merge for CustomPatch {
+    type Output = Self;
+
+    fn merge(self, _: Self) -> Self {
+        self
+    }
+}
+
+impl Patch<CustomPatch> for Goo {
+    fn patch(&mut self, _: CustomPatch) {}
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(setters)]
+struct Foo(u8);
+
+#[derive(Patch)]
+#[patch(setters)]
+struct Bar {
+    default: u8,
+}
+
+#[derive(Patch)]
+#[patch(setters = "set_", compact)]
+struct Baz {
+    a: u8,
+}
+
+#[derive(Patch)]
+#[patch(setters, minimize)]
+struct Qux {
+    minimize: u8,
+    is_noop_for: u8,
+}
+
+#[derive(Patch)]
+#[patch(setters, compact)]
+struct Quux {
+    a: u8,
+    set_a: u8,
+}
+
+#[derive(Patch)]
+#[patch(accessors)]
+struct Corge(u8);
+
+fn main() {}
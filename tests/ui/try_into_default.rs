@@ -0,0 +1,15 @@
+use patched::Patch;
+
+#[derive(Patch)]
+#[patch(try_into_default)]
+struct Foo {
+    a: u32,
+}
+
+#[derive(Patch)]
+struct Goo {
+    #[patch(default = 1)]
+    a: u32,
+}
+
+fn main() {}